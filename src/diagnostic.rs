@@ -0,0 +1,51 @@
+use std::fmt;
+
+use crate::lexer::token::Span;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Warning,
+    Error,
+}
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Warning => write!(f, "warning"),
+            Level::Error => write!(f, "error"),
+        }
+    }
+}
+
+//a message about a place in the source
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    pub span: Span,
+}
+impl Diagnostic {
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            level: Level::Warning,
+            message: message.into(),
+            span,
+        }
+    }
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            level: Level::Error,
+            message: message.into(),
+            span,
+        }
+    }
+    //`warning: message`, then the position and the line it points into
+    pub fn render(&self, source: &str, path: &str) -> String {
+        let (line, col) = self.span.line_col(source);
+        let text = source.lines().nth(line - 1).unwrap_or("");
+        let marker = " ".repeat(col - 1) + "^";
+        format!(
+            "{}: {}\n  --> {path}:{line}:{col}\n   | {text}\n   | {marker}\n",
+            self.level, self.message
+        )
+    }
+}
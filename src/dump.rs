@@ -0,0 +1,330 @@
+use crate::{
+    expression::{
+        statements::{
+            ArrayLit, Bool, Field, FieldAssign, FuncDecl, FunctionCall, IfBlock, Import,
+            Index, IndexAssign, Number, Return, Str, StructDecl, StructLit, TwoSideOp, Var, VarDecl,
+            WhileBlock,
+        },
+        visit::{walk_expr, Visitor},
+        CodeBlock, Expression, Node,
+    },
+    lexer::token::Span,
+    types::Type,
+};
+
+//stable, indented tree view of the ast (--emit ast)
+pub fn tree(program: &CodeBlock) -> String {
+    let mut printer = TreePrinter::default();
+    printer.visit_expr(program);
+    printer.out
+}
+
+#[derive(Default)]
+struct TreePrinter {
+    out: String,
+    level: usize,
+}
+impl Visitor for TreePrinter {
+    fn visit_expr(&mut self, expr: &dyn Expression) {
+        let span = expr.span();
+        let label = match expr.node() {
+            Node::Block(block) => format!("Block {}", block.block_type.get_name()),
+            Node::If(_) => String::from("If"),
+            Node::While(_) => String::from("While"),
+            Node::BinOp(op) => format!("BinOp {}", op.sign),
+            Node::Number(number) => format!("Number {}", number.0),
+            Node::Str(string) => format!("Str {}", quote(&string.0)),
+            Node::Bool(value) => format!("Bool {}", value.0),
+            Node::Var(var) => format!("Var {}{} @{}", var.name, annotation(&var.ty), var.addr),
+            Node::Call(call) => format!("Call {}", call.func.get_name()),
+            Node::FuncDecl(decl) => {
+                let args = decl
+                    .args
+                    .iter()
+                    .map(|arg| format!("{}{} @{}", arg.name, annotation(&arg.ty), arg.addr))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let inline = if decl.inline { "inline " } else { "" };
+                let ret = decl.ret.as_ref().map_or(String::new(), |ty| format!(" -> {ty}"));
+                format!("FuncDecl {inline}{}({}){ret}", decl.name, args)
+            }
+            Node::VarDecl(decl) => {
+                format!("VarDecl {}{} @{}", decl.name, annotation(&decl.ty), decl.addr)
+            }
+            Node::Return(_) => String::from("Return"),
+            Node::Array(array) if array.len.is_some() => String::from("Array repeat"),
+            Node::Array(_) => String::from("Array"),
+            Node::Index(_) => String::from("Index"),
+            Node::IndexAssign(_) => String::from("IndexAssign"),
+            Node::StructDecl(decl) => {
+                let fields = decl.fields.iter().map(|field| {
+                    format!("{}{}", field.name, annotation(&field.ty))
+                });
+                format!("StructDecl {} {{ {} }}", decl.name, fields.collect::<Vec<_>>().join(", "))
+            }
+            Node::StructLit(lit) => format!("StructLit {}", lit.name),
+            Node::Field(field) => format!("Field {} #{}", field.name, field.slot),
+            Node::FieldAssign(assign) => format!("FieldAssign {} #{}", assign.name, assign.slot),
+            Node::Import(import) => format!("Import {}", quote(&import.path)),
+        };
+        self.out.push_str(&format!(
+            "{}{} [{}..{}]\n",
+            "  ".repeat(self.level),
+            label,
+            span.start,
+            span.end
+        ));
+        self.level += 1;
+        walk_expr(self, expr);
+        self.level -= 1;
+    }
+}
+
+//json export of the ast (--emit json), every node has a "kind" and a "span"
+pub fn json(program: &CodeBlock) -> String {
+    let mut exporter = JsonExporter::default();
+    exporter.visit_expr(program);
+    exporter.out
+}
+
+#[derive(Default)]
+struct JsonExporter {
+    out: String,
+}
+impl JsonExporter {
+    fn open(&mut self, kind: &str, span: Span) {
+        self.out.push_str(&format!(
+            "{{\"kind\":{},\"span\":{{\"start\":{},\"end\":{}}}",
+            quote(kind),
+            span.start,
+            span.end
+        ));
+    }
+    fn field(&mut self, name: &str, value: &str) {
+        self.out.push_str(&format!(",{}:{}", quote(name), value));
+    }
+    fn child(&mut self, name: &str, expr: &dyn Expression) {
+        self.out.push_str(&format!(",{}:", quote(name)));
+        self.visit_expr(expr);
+    }
+    fn children(&mut self, name: &str, exprs: &[Box<dyn Expression>]) {
+        self.out.push_str(&format!(",{}:[", quote(name)));
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.visit_expr(expr.as_ref());
+        }
+        self.out.push(']');
+    }
+    fn close(&mut self) {
+        self.out.push('}');
+    }
+}
+impl Visitor for JsonExporter {
+    fn visit_block(&mut self, block: &CodeBlock) {
+        self.open("block", block.span);
+        self.field("block_type", &quote(&block.block_type.get_name()));
+        self.children("lines", &block.lines);
+        self.close();
+    }
+    fn visit_if(&mut self, if_block: &IfBlock) {
+        self.open("if", if_block.span);
+        self.child("cond", if_block.cond.as_ref());
+        self.child("code", if_block.code.as_ref());
+        self.close();
+    }
+    fn visit_while(&mut self, while_block: &WhileBlock) {
+        self.open("while", while_block.span);
+        self.child("cond", while_block.cond.as_ref());
+        self.child("code", while_block.code.as_ref());
+        self.close();
+    }
+    fn visit_bin_op(&mut self, op: &TwoSideOp) {
+        self.open("bin_op", op.span);
+        self.field("op", &quote(&op.sign));
+        self.child("lhs", op.values.0.as_ref());
+        self.child("rhs", op.values.1.as_ref());
+        self.close();
+    }
+    fn visit_number(&mut self, number: &Number) {
+        self.open("number", number.1);
+        self.field("value", &quote(&number.0));
+        self.close();
+    }
+    fn visit_str(&mut self, string: &Str) {
+        self.open("string", string.1);
+        self.field("value", &quote(&string.0));
+        self.close();
+    }
+    fn visit_bool(&mut self, value: &Bool) {
+        self.open("bool", value.1);
+        self.field("value", &value.0.to_string());
+        self.close();
+    }
+    fn visit_var(&mut self, var: &Var) {
+        self.open("var", var.span);
+        self.field("name", &quote(&var.name));
+        self.field("addr", &var.addr.to_string());
+        self.close();
+    }
+    fn visit_call(&mut self, call: &FunctionCall) {
+        self.open("call", call.span);
+        self.field("func", &quote(&call.func.get_name()));
+        self.children("args", &call.args);
+        self.close();
+    }
+    fn visit_func_decl(&mut self, decl: &FuncDecl) {
+        self.open("func_decl", decl.span);
+        self.field("name", &quote(&decl.name));
+        let args = decl
+            .args
+            .iter()
+            .map(|arg| {
+                let (name, ty) = (quote(&arg.name), json_type(&arg.ty));
+                format!("{{\"name\":{name},\"addr\":{},\"type\":{ty}}}", arg.addr)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        self.field("args", &format!("[{args}]"));
+        self.field("inline", &decl.inline.to_string());
+        self.field("ret", &json_type(&decl.ret));
+        self.child("body", decl.body.as_ref());
+        self.close();
+    }
+    fn visit_var_decl(&mut self, decl: &VarDecl) {
+        self.open("var_decl", decl.span);
+        self.field("name", &quote(&decl.name));
+        self.field("addr", &decl.addr.to_string());
+        self.field("type", &json_type(&decl.ty));
+        self.child("value", decl.value.as_ref());
+        self.close();
+    }
+    fn visit_return(&mut self, ret: &Return) {
+        self.open("return", ret.span);
+        self.child("value", ret.value.as_ref());
+        self.close();
+    }
+    fn visit_array(&mut self, array: &ArrayLit) {
+        self.open("array", array.span);
+        self.children("values", &array.values);
+        match &array.len {
+            Some(len) => self.child("len", len.as_ref()),
+            None => self.field("len", "null"),
+        }
+        self.close();
+    }
+    fn visit_index(&mut self, index: &Index) {
+        self.open("index", index.span);
+        self.child("array", index.array.as_ref());
+        self.child("index", index.index.as_ref());
+        self.close();
+    }
+    fn visit_index_assign(&mut self, assign: &IndexAssign) {
+        self.open("index_assign", assign.span);
+        self.child("array", assign.array.as_ref());
+        self.child("index", assign.index.as_ref());
+        self.child("value", assign.value.as_ref());
+        self.close();
+    }
+    fn visit_struct_decl(&mut self, decl: &StructDecl) {
+        self.open("struct_decl", decl.span);
+        self.field("name", &quote(&decl.name));
+        let fields = decl
+            .fields
+            .iter()
+            .map(|field| {
+                let (name, ty) = (quote(&field.name), json_type(&field.ty));
+                format!("{{\"name\":{name},\"type\":{ty}}}")
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        self.field("fields", &format!("[{fields}]"));
+        self.close();
+    }
+    fn visit_struct_lit(&mut self, lit: &StructLit) {
+        self.open("struct_lit", lit.span);
+        self.field("name", &quote(&lit.name));
+        self.children("values", &lit.values);
+        self.close();
+    }
+    fn visit_field(&mut self, field: &Field) {
+        self.open("field", field.span);
+        self.field("name", &quote(&field.name));
+        self.field("slot", &field.slot.to_string());
+        self.child("object", field.object.as_ref());
+        self.close();
+    }
+    fn visit_field_assign(&mut self, assign: &FieldAssign) {
+        self.open("field_assign", assign.span);
+        self.field("name", &quote(&assign.name));
+        self.field("slot", &assign.slot.to_string());
+        self.child("object", assign.object.as_ref());
+        self.child("value", assign.value.as_ref());
+        self.close();
+    }
+    fn visit_import(&mut self, import: &Import) {
+        self.open("import", import.span);
+        self.field("path", &quote(&import.path));
+        self.close();
+    }
+}
+fn annotation(ty: &Option<Type>) -> String {
+    ty.as_ref().map_or(String::new(), |ty| format!(": {ty}"))
+}
+fn json_type(ty: &Option<Type>) -> String {
+    ty.as_ref().map_or(String::from("null"), |ty| quote(&ty.to_string()))
+}
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{default_builders, parse};
+
+    const SOURCE: &str = "x sei 1; def f(a) { print(\"say \\\"hi\\\"\\n\") } f(x + 2)";
+
+    #[test]
+    fn prints_the_tree() {
+        let ast = parse(SOURCE.to_string(), &default_builders());
+        let expected = "\
+Block curl [0..52]
+  VarDecl x @0 [0..7]
+    Number 1 [6..7]
+  FuncDecl f(a @0) [9..43]
+    Block curl [18..43]
+      Call print [20..41]
+        Str \"say \\\"hi\\\"\\n\" [26..40]
+  Call f [44..52]
+    BinOp + [46..51]
+      Var x @0 [46..47]
+      Number 2 [50..51]
+";
+        assert_eq!(tree(&ast), expected);
+    }
+    #[test]
+    fn exports_json() {
+        let ast = parse(SOURCE.to_string(), &default_builders());
+        let json = json(&ast);
+        let decl = r#"{"kind":"var_decl","span":{"start":0,"end":7},"name":"x","addr":0,"type":null,"#;
+        assert!(json.starts_with(r#"{"kind":"block","span":{"start":0,"end":52},"block_type":"curl""#));
+        assert!(json.contains(decl));
+        assert!(json.contains(r#""args":[{"name":"a","addr":0,"type":null}],"inline":false"#));
+        assert!(json.contains(r#"{"kind":"string","span":{"start":26,"end":40},"value":"say \"hi\"\n"}"#));
+        assert!(json.contains(r#""lhs":{"kind":"var","span":{"start":46,"end":47},"name":"x","addr":0}"#));
+    }
+}
@@ -1,15 +1,76 @@
 use core::fmt;
 
-use self::statements::FrameStack;
+use crate::lexer::token::Span;
+
+use self::statements::{
+    ArrayLit, Bool, Field, FieldAssign, FrameStack, FuncDecl, FunctionCall, IfBlock, Import,
+    Index, IndexAssign, Number, Return, Str, StructDecl, StructLit, TwoSideOp, Var, VarDecl,
+    WhileBlock,
+};
 
 pub mod statements;
+pub mod visit;
 pub trait Expression: ExpressionClone + fmt::Debug {
-    fn gen_mips(&self) -> String;
     fn get_name(&self) -> String;
+    //concrete view of the node, so passes can match on the kind instead of comparing names
+    fn node(&self) -> Node<'_>;
+    fn node_mut(&mut self) -> NodeMut<'_>;
+    fn span(&self) -> Span;
+    fn set_span(&mut self, span: Span);
     fn as_block(&self) -> Option<&CodeBlock> {
-        None
+        match self.node() {
+            Node::Block(block) => Some(block),
+            _ => None,
+        }
     }
 }
+//typed AST: one variant per expression struct, borrowed from the parsed tree
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Block(&'a CodeBlock),
+    If(&'a IfBlock),
+    While(&'a WhileBlock),
+    BinOp(&'a TwoSideOp),
+    Number(&'a Number),
+    Str(&'a Str),
+    Bool(&'a Bool),
+    Var(&'a Var),
+    Call(&'a FunctionCall),
+    FuncDecl(&'a FuncDecl),
+    VarDecl(&'a VarDecl),
+    Return(&'a Return),
+    Array(&'a ArrayLit),
+    Index(&'a Index),
+    IndexAssign(&'a IndexAssign),
+    StructDecl(&'a StructDecl),
+    StructLit(&'a StructLit),
+    Field(&'a Field),
+    FieldAssign(&'a FieldAssign),
+    Import(&'a Import),
+}
+#[derive(Debug)]
+pub enum NodeMut<'a> {
+    Block(&'a mut CodeBlock),
+    If(&'a mut IfBlock),
+    While(&'a mut WhileBlock),
+    BinOp(&'a mut TwoSideOp),
+    Number(&'a mut Number),
+    Str(&'a mut Str),
+    Bool(&'a mut Bool),
+    Var(&'a mut Var),
+    Call(&'a mut FunctionCall),
+    FuncDecl(&'a mut FuncDecl),
+    VarDecl(&'a mut VarDecl),
+    Return(&'a mut Return),
+    Array(&'a mut ArrayLit),
+    Index(&'a mut Index),
+    IndexAssign(&'a mut IndexAssign),
+    StructDecl(&'a mut StructDecl),
+    StructLit(&'a mut StructLit),
+    Field(&'a mut Field),
+    FieldAssign(&'a mut FieldAssign),
+    Import(&'a mut Import),
+}
 pub trait ExpressionClone {
     fn clone_box(&self) -> Box<dyn Expression>;
 }
@@ -26,7 +87,7 @@ impl Clone for Box<dyn Expression> {
         self.clone_box()
     }
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum BlockType {
     Curl,
     Brack,
@@ -42,30 +103,37 @@ impl BlockType {
 
 #[derive(Clone, Debug)]
 pub struct CodeBlock {
-    block_type: BlockType,
-    lines: Vec<Box<dyn Expression>>,
+    pub block_type: BlockType,
+    pub lines: Vec<Box<dyn Expression>>,
     pub frame: Option<FrameStack>,
+    pub span: Span,
 }
 
 impl CodeBlock {
     pub fn new(lines: Vec<Box<dyn Expression>>, block_type: BlockType, frame: Option<FrameStack>) -> Self {
-        Self { block_type, lines, frame }
+        Self {
+            block_type,
+            lines,
+            frame,
+            span: Span::default(),
+        }
     }
 }
 
 impl Expression for CodeBlock {
-    fn gen_mips(&self) -> String {
-        self.lines
-            .iter()
-            .map(|l| l.gen_mips())
-            .collect::<Vec<_>>()
-            .join("\n")
-    }
-
     fn get_name(&self) -> String {
         self.block_type.get_name()
     }
-    fn as_block(&self) -> Option<&CodeBlock> {
-        Some(self)
+    fn node(&self) -> Node<'_> {
+        Node::Block(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Block(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
     }
 }
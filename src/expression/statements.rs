@@ -1,41 +1,48 @@
 use core::fmt;
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use rand::random;
-
 use crate::{
-    mips,
+    ir::{runtime::Runtime, BinOp, Callee},
+    lexer::token::Span,
     pattern::{
-        BlockPatt, ExprBuilder, ExprConstr, ExprPattern, SimplePattern, TextPatt, TextPattVar,
+        BlockPatt, ExprBuilder, ExprConstr, ExprPattern, FieldPatt, IndexPatt, MemberPatt,
+        NamePatt, OneOfPatt, SimplePattern, SubscriptPatt, TextPatt, TextPattVar,
     },
+    types::Type,
 };
 
-use super::{BlockType, CodeBlock, Expression};
+use super::{BlockType, Expression, Node, NodeMut};
 
 #[derive(Clone, Debug)]
 pub struct IfBlock {
-    code: Box<dyn Expression>,
-    cond: Box<dyn Expression>,
+    pub code: Box<dyn Expression>,
+    pub cond: Box<dyn Expression>,
+    pub span: Span,
 }
 impl Expression for IfBlock {
-    fn gen_mips(&self) -> String {
-        let id = random::<u32>();
-        let cond_mips = self.cond.gen_mips();
-        let if_mips = mips::pop() + &format!("#branch:\nbeqz $t0, if_false{id}\n");
-
-        cond_mips
-            + &if_mips
-            + "#branch code: \n"
-            + &self.code.gen_mips()
-            + &format!("if_false{id}:\n")
-    }
     fn get_name(&self) -> String {
         String::from("if")
     }
+    fn node(&self) -> Node<'_> {
+        Node::If(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::If(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 impl IfBlock {
     fn new(code: Box<dyn Expression>, cond: Box<dyn Expression>) -> Self {
-        Self { code, cond }
+        Self {
+            code,
+            cond,
+            span: Span::default(),
+        }
     }
     fn construct(mut params: Vec<Box<dyn Expression>>, _: &mut FrameStack) -> Box<dyn Expression> {
         assert_eq!(params.len(), 2);
@@ -56,28 +63,34 @@ impl IfBlock {
 //while
 #[derive(Clone, Debug)]
 pub struct WhileBlock {
-    code: Box<dyn Expression>,
-    cond: Box<dyn Expression>,
+    pub code: Box<dyn Expression>,
+    pub cond: Box<dyn Expression>,
+    pub span: Span,
 }
 impl Expression for WhileBlock {
-    fn gen_mips(&self) -> String {
-        let id: u32 = random();
-        let cond_mips = self.cond.gen_mips();
-        let while_mips = mips::pop() + &format!("#branch:\nbeqz $t0, while_end{id}\n");
-        format!("while_start{id}:\n#calc cond\n")
-            + &cond_mips
-            + "#eval cond: \n"
-            + &while_mips
-            + &self.code.gen_mips()
-            + &format!("j while_start{id}\nwhile_end{id}:\n")
-    }
     fn get_name(&self) -> String {
         String::from("while")
     }
+    fn node(&self) -> Node<'_> {
+        Node::While(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::While(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 impl WhileBlock {
     fn new(code: Box<dyn Expression>, cond: Box<dyn Expression>) -> Self {
-        Self { code, cond }
+        Self {
+            code,
+            cond,
+            span: Span::default(),
+        }
     }
     fn construct(mut params: Vec<Box<dyn Expression>>, _: &mut FrameStack) -> Box<dyn Expression> {
         assert_eq!(params.len(), 2);
@@ -97,66 +110,135 @@ impl WhileBlock {
 }
 #[derive(Clone, Debug)]
 pub struct TwoSideOp {
-    values: (Box<dyn Expression>, Box<dyn Expression>),
-    sign: String,
-    mips: String,
+    pub values: (Box<dyn Expression>, Box<dyn Expression>),
+    pub sign: String,
+    pub op: BinOp,
+    pub span: Span,
 }
 
 impl Expression for TwoSideOp {
-    fn gen_mips(&self) -> String {
-        self.values.0.gen_mips()
-            + &self.values.1.gen_mips()
-            + &mips::pop_two()
-            + &self.mips
-            + "\n"
-            + &mips::save_t0()
-    }
-
     fn get_name(&self) -> String {
         self.sign.clone()
     }
+    fn node(&self) -> Node<'_> {
+        Node::BinOp(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::BinOp(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 impl TwoSideOp {
     pub fn new(
         values: (Box<dyn Expression>, Box<dyn Expression>),
         sign: String,
-        mips: String,
+        op: BinOp,
     ) -> Self {
-        Self { values, sign, mips }
+        Self {
+            values,
+            sign,
+            op,
+            span: Span::default(),
+        }
     }
-    pub fn get_builder(sign: &str, mips: &str) -> ExprBuilder {
+    pub fn get_builder(op: BinOp) -> ExprBuilder {
+        Self::get_group_builder(&[op])
+    }
+    //operators in the same group bind equally strong and are parsed left to right
+    pub fn get_group_builder(ops: &[BinOp]) -> ExprBuilder {
         let patterns: Vec<Box<dyn SimplePattern>> = vec![
             Box::new(ExprPattern),
-            Box::new(TextPatt(sign.to_string())),
+            Box::new(OneOfPatt(ops.iter().map(|op| op.sign().to_string()).collect())),
             Box::new(ExprPattern),
         ];
-        let sign = sign.to_string();
-        let mips = mips.to_string();
+        let ops = ops
+            .iter()
+            .map(|op| (op.sign().to_string(), *op))
+            .collect::<HashMap<_, _>>();
         let constructor: ExprConstr = Box::new(move |mut params, _| {
             let b = params.pop().unwrap();
+            let sign = params.pop().unwrap().get_name();
             let a = params.pop().unwrap();
-            Box::new(Self {
-                values: (a, b),
-                sign: sign.clone(),
-                mips: mips.clone(),
-            })
+            let op = ops[&sign];
+            Box::new(Self::new((a, b), sign, op))
         });
-
         ExprBuilder::new(patterns, constructor)
     }
 }
+//the literal as written: 12, 0xff, 1_000, 'a' or 3.5
 #[derive(Clone, Debug)]
-pub struct Number(pub String);
-impl Expression for Number {
-    fn gen_mips(&self) -> String {
-        let value = &self.0;
-        mips::push_value(value)
+pub struct Number(pub String, pub Span);
+impl Number {
+    pub fn new(value: String) -> Self {
+        Self(value, Span::default())
+    }
+    pub fn is_float(&self) -> bool {
+        !self.0.starts_with('\'') && self.0.contains('.')
     }
+}
+impl Expression for Number {
     fn get_name(&self) -> String {
         String::from("number")
     }
+    fn node(&self) -> Node<'_> {
+        Node::Number(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Number(self)
+    }
+    fn span(&self) -> Span {
+        self.1
+    }
+    fn set_span(&mut self, span: Span) {
+        self.1 = span;
+    }
 }
+//string literal, without the quotes and with the escapes resolved
 #[derive(Clone, Debug)]
+pub struct Str(pub String, pub Span);
+impl Expression for Str {
+    fn get_name(&self) -> String {
+        String::from("string")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::Str(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Str(self)
+    }
+    fn span(&self) -> Span {
+        self.1
+    }
+    fn set_span(&mut self, span: Span) {
+        self.1 = span;
+    }
+}
+//`true` or `false`
+#[derive(Clone, Debug)]
+pub struct Bool(pub bool, pub Span);
+impl Expression for Bool {
+    fn get_name(&self) -> String {
+        String::from("bool")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::Bool(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Bool(self)
+    }
+    fn span(&self) -> Span {
+        self.1
+    }
+    fn set_span(&mut self, span: Span) {
+        self.1 = span;
+    }
+}
+#[derive(Clone, Debug, Default)]
 pub struct FrameLayer {
     vars: HashMap<String, usize>,
 }
@@ -175,13 +257,8 @@ impl FrameLayer {
             addr
         }
     }
-    //generate mips
-    pub fn gen_mips(&self) -> String {
-        let n = self.vars.len();
-        format!("add $t6, $sp, $zero\naddi $sp, $sp, {n}\n")
-    }
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct FrameStack {
     layers: Vec<Rc<FrameLayer>>,
     top: FrameLayer,
@@ -206,6 +283,9 @@ impl FrameStack {
         }
     }
     pub fn get_addr(&mut self, name: &str) -> usize {
+        if let Some(addr) = self.top.vars.get(name) {
+            return *addr;
+        }
         for layer in self.layers.iter().rev() {
             if let Some(addr) = layer.vars.get(name) {
                 return *addr;
@@ -214,42 +294,82 @@ impl FrameStack {
         *self.n_vars.borrow_mut() += 1;
         self.top.get_addr(name, *self.n_vars.borrow() - 1)
     }
-    pub fn gen_mips(&self) -> String {
-        let n = *self.n_vars.borrow();
-        format!("add $t6, $sp, $zero\naddi $sp, $sp, {n}\n")
+    //number of slots handed out so far, shared by all layers of one function
+    pub fn n_slots(&self) -> usize {
+        *self.n_vars.borrow()
+    }
+    //a slot without a name, for variables moved in from another frame
+    pub fn new_slot(&self) -> usize {
+        *self.n_vars.borrow_mut() += 1;
+        *self.n_vars.borrow() - 1
     }
 }
 #[derive(Clone, Debug)]
 pub struct Var {
-    name: String,
-    addr: usize,
+    pub name: String,
+    pub addr: usize,
+    //`a: int`, only parameters keep it
+    pub ty: Option<Type>,
+    pub span: Span,
 }
 
 impl Var {
     pub fn new(name: String, addr: usize) -> Self {
-        Self { name, addr }
+        Self {
+            name,
+            addr,
+            ty: None,
+            span: Span::default(),
+        }
+    }
+    pub fn get_typed_builder() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> = vec![
+            Box::new(NamePatt),
+            Box::new(TextPatt(String::from(":"))),
+            Box::new(type_patt()),
+        ];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let ty = Type::from_name(&params.pop().unwrap().get_name());
+            let name = params.pop().unwrap();
+            let mut var = Self::new(name.get_name(), 0);
+            var.ty = ty;
+            Box::new(var)
+        });
+        ExprBuilder::new(patterns, constructor)
     }
 }
+//one of the type names, returned like OneOfPatt does
+fn type_patt() -> OneOfPatt {
+    OneOfPatt(Type::ALL.iter().map(Type::to_string).collect())
+}
 
 impl Expression for Var {
-    fn gen_mips(&self) -> String {
-        mips::load_var(self.addr as i32)
-    }
-
     fn get_name(&self) -> String {
         self.name.clone()
     }
+    fn node(&self) -> Node<'_> {
+        Node::Var(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Var(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 pub trait Function {
-    fn get_call_mips(&self) -> String;
+    fn callee(&self) -> Callee;
     fn get_name(&self) -> String;
 }
 #[derive(Clone, Debug)]
 struct UnknownFn(String);
 impl Function for UnknownFn {
-    fn get_call_mips(&self) -> String {
-        format!("jal {}\n", self.0)
+    fn callee(&self) -> Callee {
+        Callee::Func(self.0.clone())
     }
     fn get_name(&self) -> String {
         self.0.clone()
@@ -257,12 +377,17 @@ impl Function for UnknownFn {
 }
 #[derive(Clone)]
 pub struct FunctionCall {
-    func: Rc<dyn Function>,
-    args: Vec<Box<dyn Expression>>,
+    pub func: Rc<dyn Function>,
+    pub args: Vec<Box<dyn Expression>>,
+    pub span: Span,
 }
 impl FunctionCall {
     fn new(func: Rc<dyn Function>, args: Vec<Box<dyn Expression>>) -> Self {
-        Self { func, args }
+        Self {
+            func,
+            args,
+            span: Span::default(),
+        }
     }
 
     pub fn get_builder(func: Rc<dyn Function>) -> ExprBuilder {
@@ -275,14 +400,13 @@ impl FunctionCall {
             let args = params.pop().unwrap();
             let args = args.as_block().unwrap();
             let args = args.lines.clone();
-            let func = Box::new(Self::new(func.clone(), args));
-            func
+            Box::new(Self::new(func.clone(), args))
         });
         ExprBuilder::new(patterns, constructor)
     }
     pub fn get_builder_var() -> ExprBuilder {
         let patterns: Vec<Box<dyn SimplePattern>> =
-            vec![Box::new(TextPattVar), Box::new(BlockPatt(BlockType::Brack))];
+            vec![Box::new(NamePatt), Box::new(BlockPatt(BlockType::Brack))];
         let constructor: ExprConstr = Box::new(move |mut params, _| {
             assert_eq!(params.len(), 2);
             let args = params.pop().unwrap();
@@ -290,53 +414,220 @@ impl FunctionCall {
             let args = args.lines.clone();
             let name = params.pop().unwrap();
             let name = name.get_name();
-            let func = Box::new(Self::new(Rc::new(UnknownFn(name)), args));
-            func
+            Box::new(Self::new(Rc::new(UnknownFn(name)), args))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+    //`math.gcd(a, b)`, a function of an imported module
+    pub fn get_builder_qualified() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> = vec![
+            Box::new(NamePatt),
+            Box::new(MemberPatt),
+            Box::new(BlockPatt(BlockType::Brack)),
+        ];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let args = params.pop().unwrap();
+            let args = args.as_block().unwrap().lines.clone();
+            let member = params.pop().unwrap().get_name();
+            let module = params.pop().unwrap().get_name();
+            Box::new(Self::new(Rc::new(UnknownFn(module + &member)), args))
         });
         ExprBuilder::new(patterns, constructor)
     }
+    //calls another function with the same arguments, the modules give the functions their
+    //final names
+    pub fn rename(&mut self, name: String) {
+        self.func = Rc::new(UnknownFn(name));
+    }
 }
 impl fmt::Debug for FunctionCall {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "func call: {}({:#?})", self.get_name(), self.args)
+        f.debug_struct("FunctionCall")
+            .field("func", &self.func.get_name())
+            .field("args", &self.args)
+            .field("span", &self.span)
+            .finish()
     }
 }
 impl Expression for FunctionCall {
-    fn gen_mips(&self) -> String {
-        self.args
-            .iter()
-            .map(|arg| arg.gen_mips())
-            .collect::<Vec<_>>()
-            .join("\n")
-            + &self.func.get_call_mips()
-    }
     fn get_name(&self) -> String {
         String::from("func")
     }
+    fn node(&self) -> Node<'_> {
+        Node::Call(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Call(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 pub struct PrintFn;
 impl Function for PrintFn {
-    fn get_call_mips(&self) -> String {
-        mips::syscall(1)
+    fn callee(&self) -> Callee {
+        Callee::Syscall {
+            code: 1,
+            returns: false,
+        }
     }
 
     fn get_name(&self) -> String {
         String::from("print")
     }
 }
+//print of a string, the type checker puts it in place of PrintFn
+pub struct PrintStrFn;
+impl Function for PrintStrFn {
+    fn callee(&self) -> Callee {
+        Callee::Syscall {
+            code: 4,
+            returns: false,
+        }
+    }
+
+    fn get_name(&self) -> String {
+        String::from("print")
+    }
+}
+//print of a float, like PrintStrFn
+pub struct PrintFloatFn;
+impl Function for PrintFloatFn {
+    fn callee(&self) -> Callee {
+        Callee::Syscall {
+            code: 2,
+            returns: false,
+        }
+    }
+
+    fn get_name(&self) -> String {
+        String::from("print")
+    }
+}
+//`float(i)` and `int(x)`
+pub struct ToFloatFn;
+impl Function for ToFloatFn {
+    fn callee(&self) -> Callee {
+        Callee::ToFloat
+    }
+
+    fn get_name(&self) -> String {
+        String::from("float")
+    }
+}
+pub struct ToIntFn;
+impl Function for ToIntFn {
+    fn callee(&self) -> Callee {
+        Callee::ToInt
+    }
+
+    fn get_name(&self) -> String {
+        String::from("int")
+    }
+}
+//reading input: `read_int()` and `read_char()` give what they read, `read_string(buf, len)`
+//reads a line of at most len - 1 characters into buf, which `buffer(n)` makes
+pub struct ReadIntFn;
+impl Function for ReadIntFn {
+    fn callee(&self) -> Callee {
+        Callee::Syscall {
+            code: 5,
+            returns: true,
+        }
+    }
+
+    fn get_name(&self) -> String {
+        String::from("read_int")
+    }
+}
+pub struct ReadCharFn;
+impl Function for ReadCharFn {
+    fn callee(&self) -> Callee {
+        Callee::Syscall {
+            code: 12,
+            returns: true,
+        }
+    }
+
+    fn get_name(&self) -> String {
+        String::from("read_char")
+    }
+}
+pub struct ReadStringFn;
+impl Function for ReadStringFn {
+    fn callee(&self) -> Callee {
+        Callee::Syscall {
+            code: 8,
+            returns: false,
+        }
+    }
+
+    fn get_name(&self) -> String {
+        String::from("read_string")
+    }
+}
+pub struct BufferFn;
+impl Function for BufferFn {
+    fn callee(&self) -> Callee {
+        Callee::Runtime(Runtime::Buffer)
+    }
+
+    fn get_name(&self) -> String {
+        String::from("buffer")
+    }
+}
+//vectors on the heap: `alloc(n)`, `push(v, x)`, and `len(v)`, which takes arrays as well
+pub struct AllocFn;
+impl Function for AllocFn {
+    fn callee(&self) -> Callee {
+        Callee::Runtime(Runtime::Alloc)
+    }
+
+    fn get_name(&self) -> String {
+        String::from("alloc")
+    }
+}
+pub struct PushFn;
+impl Function for PushFn {
+    fn callee(&self) -> Callee {
+        Callee::Runtime(Runtime::Push)
+    }
+
+    fn get_name(&self) -> String {
+        String::from("push")
+    }
+}
+pub struct LenFn;
+impl Function for LenFn {
+    fn callee(&self) -> Callee {
+        Callee::Len
+    }
+
+    fn get_name(&self) -> String {
+        String::from("len")
+    }
+}
 //func declaration
 #[derive(Clone, Debug)]
 pub struct FuncDecl {
-    name: String,
-    body: Box<dyn Expression>,
-    args: Vec<usize>,
-    frame: FrameStack,
+    pub name: String,
+    pub body: Box<dyn Expression>,
+    pub args: Vec<Var>,
+    pub frame: FrameStack,
+    //`inline def`, gets inlined whatever its size
+    pub inline: bool,
+    //`-> int`
+    pub ret: Option<Type>,
+    pub span: Span,
 }
 impl FuncDecl {
     pub fn new(
         name: String,
         body: Box<dyn Expression>,
-        args: Vec<usize>,
+        args: Vec<Var>,
         frame: FrameStack,
     ) -> Self {
         Self {
@@ -344,20 +635,32 @@ impl FuncDecl {
             body,
             args,
             frame,
+            inline: false,
+            ret: None,
+            span: Span::default(),
         }
     }
-    pub fn get_builder() -> ExprBuilder {
-        let patterns: Vec<Box<dyn SimplePattern>> = vec![
+    pub fn get_builder(inline: bool, typed: bool) -> ExprBuilder {
+        let mut patterns: Vec<Box<dyn SimplePattern>> = vec![
             Box::new(TextPatt(String::from("def"))),
             Box::new(TextPattVar),
             Box::new(BlockPatt(BlockType::Brack)),
             Box::new(BlockPatt(BlockType::Curl)),
         ];
-        let constructor: Box<
-            dyn Fn(Vec<Box<dyn Expression>>, &mut FrameStack) -> Box<dyn Expression>,
-        > = Box::new(move |mut params, frame| {
+        if inline {
+            patterns.insert(0, Box::new(TextPatt(String::from("inline"))));
+        }
+        if typed {
+            patterns.insert(patterns.len() - 1, Box::new(TextPatt(String::from("->"))));
+            patterns.insert(patterns.len() - 1, Box::new(type_patt()));
+        }
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
             let brack_param = params.pop().unwrap();
             let body = brack_param.as_block().unwrap();
+            let ret = match typed {
+                true => Type::from_name(&params.pop().unwrap().get_name()),
+                false => None,
+            };
             let args = params.pop().unwrap();
             let args = args.as_block().unwrap();
             let name = params.pop().unwrap();
@@ -365,38 +668,45 @@ impl FuncDecl {
             let args = args
                 .lines
                 .iter()
-                .map(|var| frame.get_addr(&var.get_name()))
+                .map(|var| {
+                    let name = var.get_name();
+                    let addr = frame.get_addr(&name);
+                    let mut arg = Var::new(name, addr);
+                    if let Node::Var(typed) = var.node() {
+                        arg.ty = typed.ty.clone();
+                    }
+                    arg
+                })
                 .collect();
             let name = name.get_name();
-            let func = Box::new(Self::new(name, brack_param, args, frame));
-            func
+            let mut decl = Self::new(name, brack_param, args, frame);
+            decl.inline = inline;
+            decl.ret = ret;
+            Box::new(decl)
         });
         ExprBuilder::new(patterns, constructor)
     }
 }
 impl Expression for FuncDecl {
-    fn gen_mips(&self) -> String {
-        format!("j {}_end\n{}:\n", self.name, self.name)
-            + &String::from("add $t4, $t6, $zero\n") //save old base pointer
-            + &String::from("add $t5, $sp, $zero\n") //save old stack pointer
-            + &self.frame.gen_mips()
-            + &self.args.iter().rev().enumerate().map(|(i, addr)| mips::load_var(-(i as i32+1))+&mips::save_var(*addr)).collect::<Vec<_>>().join("\n")
-            + &self.body.gen_mips()
-            + &mips::pop()
-            + &String::from("add $sp, $t5, $zero\n")
-            + &String::from("add $t6, $t4, $zero\n")
-            + &mips::save_t0()
-            + &String::from("jr $ra\n")
-            + &format!("{}_end:", self.name)
-    }
-
     fn get_name(&self) -> String {
         self.name.clone()
     }
+    fn node(&self) -> Node<'_> {
+        Node::FuncDecl(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::FuncDecl(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 impl Function for FuncDecl {
-    fn get_call_mips(&self) -> String {
-        format!("jal {}\n", self.name)
+    fn callee(&self) -> Callee {
+        Callee::Func(self.name.clone())
     }
 
     fn get_name(&self) -> String {
@@ -407,38 +717,505 @@ impl Function for FuncDecl {
 //variable declaration
 #[derive(Clone, Debug)]
 pub struct VarDecl {
-    name: String,
-    addr: usize,
-    value: Box<dyn Expression>,
+    pub name: String,
+    pub addr: usize,
+    pub value: Box<dyn Expression>,
+    //`x: int sei 5`
+    pub ty: Option<Type>,
+    pub span: Span,
 }
 impl VarDecl {
     pub fn new(name: String, addr: usize, value: Box<dyn Expression>) -> Self {
-        Self { name, addr, value }
+        Self {
+            name,
+            addr,
+            value,
+            ty: None,
+            span: Span::default(),
+        }
     }
-    pub fn get_builder() -> ExprBuilder {
-        let patterns: Vec<Box<dyn SimplePattern>> = vec![
+    pub fn get_builder(typed: bool) -> ExprBuilder {
+        let mut patterns: Vec<Box<dyn SimplePattern>> = vec![
             Box::new(TextPattVar),
             Box::new(TextPatt(String::from("sei"))),
             Box::new(ExprPattern),
         ];
-        let constructor: Box<
-            dyn Fn(Vec<Box<dyn Expression>>, &mut FrameStack) -> Box<dyn Expression>,
-        > = Box::new(move |mut params, frame| {
+        if typed {
+            patterns.insert(1, Box::new(TextPatt(String::from(":"))));
+            patterns.insert(2, Box::new(type_patt()));
+        }
+        let constructor: ExprConstr = Box::new(move |mut params, frame| {
             let value = params.pop().unwrap();
+            let ty = match typed {
+                true => Type::from_name(&params.pop().unwrap().get_name()),
+                false => None,
+            };
             let name = params.pop().unwrap();
             let name = name.get_name();
             let addr = frame.get_addr(&name);
-            let var = Box::new(Self::new(name, addr, value));
-            var
+            let mut decl = Self::new(name, addr, value);
+            decl.ty = ty;
+            Box::new(decl)
         });
         ExprBuilder::new(patterns, constructor)
     }
 }
 impl Expression for VarDecl {
-    fn gen_mips(&self) -> String {
-        self.value.gen_mips() + &mips::save_var(self.addr)
-    }
     fn get_name(&self) -> String {
         String::from("var decl")
     }
+    fn node(&self) -> Node<'_> {
+        Node::VarDecl(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::VarDecl(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+//return
+#[derive(Clone, Debug)]
+pub struct Return {
+    pub value: Box<dyn Expression>,
+    pub span: Span,
+}
+impl Return {
+    pub fn new(value: Box<dyn Expression>) -> Self {
+        Self {
+            value,
+            span: Span::default(),
+        }
+    }
+    pub fn get_builder() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> = vec![
+            Box::new(TextPatt(String::from("return"))),
+            Box::new(ExprPattern),
+        ];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let value = params.pop().unwrap();
+            Box::new(Self::new(value))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+}
+impl Expression for Return {
+    fn get_name(&self) -> String {
+        String::from("return")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::Return(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Return(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+//`[1, 2, 3]`, or `[0; 10]` with a single value and the length
+#[derive(Clone, Debug)]
+pub struct ArrayLit {
+    pub values: Vec<Box<dyn Expression>>,
+    pub len: Option<Box<dyn Expression>>,
+    pub span: Span,
+}
+impl ArrayLit {
+    pub fn new(values: Vec<Box<dyn Expression>>, len: Option<Box<dyn Expression>>) -> Self {
+        Self {
+            values,
+            len,
+            span: Span::default(),
+        }
+    }
+}
+impl Expression for ArrayLit {
+    fn get_name(&self) -> String {
+        String::from("array")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::Array(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Array(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+//`a[i]`, its builder makes field accesses as well so that `a[i].x[j]` goes left to right
+#[derive(Clone, Debug)]
+pub struct Index {
+    pub array: Box<dyn Expression>,
+    pub index: Box<dyn Expression>,
+    pub span: Span,
+}
+impl Index {
+    pub fn new(array: Box<dyn Expression>, index: Box<dyn Expression>) -> Self {
+        Self {
+            array,
+            index,
+            span: Span::default(),
+        }
+    }
+    pub fn get_builder() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> =
+            vec![Box::new(ExprPattern), Box::new(SubscriptPatt)];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let index = params.pop().unwrap();
+            let array = params.pop().unwrap();
+            if let Node::Var(var) = index.node() {
+                if let Some(name) = var.name.strip_prefix('.') {
+                    return Box::new(Field::new(array, name.to_string()));
+                }
+            }
+            Box::new(Self::new(array, index))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+}
+impl Expression for Index {
+    fn get_name(&self) -> String {
+        String::from("index")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::Index(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Index(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+//`a[i] sei v`
+#[derive(Clone, Debug)]
+pub struct IndexAssign {
+    pub array: Box<dyn Expression>,
+    pub index: Box<dyn Expression>,
+    pub value: Box<dyn Expression>,
+    pub span: Span,
+}
+impl IndexAssign {
+    pub fn new(
+        array: Box<dyn Expression>,
+        index: Box<dyn Expression>,
+        value: Box<dyn Expression>,
+    ) -> Self {
+        Self {
+            array,
+            index,
+            value,
+            span: Span::default(),
+        }
+    }
+    pub fn get_builder() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> = vec![
+            Box::new(IndexPatt),
+            Box::new(TextPatt(String::from("sei"))),
+            Box::new(ExprPattern),
+        ];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let value = params.pop().unwrap();
+            let target = params.pop().unwrap();
+            let Node::Index(target) = target.node() else {
+                unreachable!("IndexPatt only matches indexing")
+            };
+            Box::new(Self::new(target.array.clone(), target.index.clone(), value))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+}
+impl Expression for IndexAssign {
+    fn get_name(&self) -> String {
+        String::from("index assign")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::IndexAssign(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::IndexAssign(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+//`struct Point { x, y }`, the fields can have types like variables
+#[derive(Clone, Debug)]
+pub struct StructDecl {
+    pub name: String,
+    pub fields: Vec<Var>,
+    pub span: Span,
+}
+impl StructDecl {
+    pub fn new(name: String, fields: Vec<Var>) -> Self {
+        Self {
+            name,
+            fields,
+            span: Span::default(),
+        }
+    }
+    pub fn get_builder() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> = vec![
+            Box::new(TextPatt(String::from("struct"))),
+            Box::new(NamePatt),
+            Box::new(BlockPatt(BlockType::Curl)),
+        ];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let fields = params.pop().unwrap();
+            let fields = fields
+                .as_block()
+                .unwrap()
+                .lines
+                .iter()
+                .map(|field| match field.node() {
+                    Node::Var(var) => var.clone(),
+                    _ => panic!("struct fields must be names, found {}", field.get_name()),
+                })
+                .collect();
+            let name = params.pop().unwrap();
+            Box::new(Self::new(name.get_name(), fields))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+}
+impl Expression for StructDecl {
+    fn get_name(&self) -> String {
+        String::from("struct")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::StructDecl(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::StructDecl(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+//`Point { 1, 2 }`, the values in the order of the fields. structs live on the heap
+#[derive(Clone, Debug)]
+pub struct StructLit {
+    pub name: String,
+    pub values: Vec<Box<dyn Expression>>,
+    pub span: Span,
+}
+impl StructLit {
+    pub fn new(name: String, values: Vec<Box<dyn Expression>>) -> Self {
+        Self {
+            name,
+            values,
+            span: Span::default(),
+        }
+    }
+    pub fn get_builder() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> =
+            vec![Box::new(NamePatt), Box::new(BlockPatt(BlockType::Curl))];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let values = params.pop().unwrap();
+            let values = values.as_block().unwrap().lines.clone();
+            let name = params.pop().unwrap();
+            Box::new(Self::new(name.get_name(), values))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+    //`geo.Point { 1, 2 }`, a struct of an imported module
+    pub fn get_builder_qualified() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> = vec![
+            Box::new(NamePatt),
+            Box::new(MemberPatt),
+            Box::new(BlockPatt(BlockType::Curl)),
+        ];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let values = params.pop().unwrap();
+            let values = values.as_block().unwrap().lines.clone();
+            let member = params.pop().unwrap().get_name();
+            let module = params.pop().unwrap().get_name();
+            Box::new(Self::new(module + &member, values))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+}
+impl Expression for StructLit {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+    fn node(&self) -> Node<'_> {
+        Node::StructLit(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::StructLit(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+//`p.x`, `slot` is the word of the struct the field is in, the type checker sets it
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub object: Box<dyn Expression>,
+    pub name: String,
+    pub slot: usize,
+    pub span: Span,
+}
+impl Field {
+    pub fn new(object: Box<dyn Expression>, name: String) -> Self {
+        Self {
+            object,
+            name,
+            slot: 0,
+            span: Span::default(),
+        }
+    }
+}
+impl Expression for Field {
+    fn get_name(&self) -> String {
+        String::from("field")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::Field(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Field(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+//`p.x sei v`
+#[derive(Clone, Debug)]
+pub struct FieldAssign {
+    pub object: Box<dyn Expression>,
+    pub name: String,
+    pub slot: usize,
+    pub value: Box<dyn Expression>,
+    pub span: Span,
+}
+impl FieldAssign {
+    pub fn new(object: Box<dyn Expression>, name: String, value: Box<dyn Expression>) -> Self {
+        Self {
+            object,
+            name,
+            slot: 0,
+            value,
+            span: Span::default(),
+        }
+    }
+    pub fn get_builder() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> = vec![
+            Box::new(FieldPatt),
+            Box::new(TextPatt(String::from("sei"))),
+            Box::new(ExprPattern),
+        ];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let value = params.pop().unwrap();
+            let target = params.pop().unwrap();
+            let Node::Field(target) = target.node() else {
+                unreachable!("FieldPatt only matches fields")
+            };
+            Box::new(Self::new(target.object.clone(), target.name.clone(), value))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+}
+impl Expression for FieldAssign {
+    fn get_name(&self) -> String {
+        String::from("field assign")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::FieldAssign(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::FieldAssign(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+//`import "utils/math.src"` or the short `use math`, which is `import "math.src"`. the path is
+//relative to the file the import is in, the functions of the module are called `math.gcd(..)`
+#[derive(Clone, Debug)]
+pub struct Import {
+    pub path: String,
+    pub short: bool,
+    pub span: Span,
+}
+impl Import {
+    pub fn new(path: String, short: bool) -> Self {
+        Self {
+            path,
+            short,
+            span: Span::default(),
+        }
+    }
+    pub fn get_builder() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> =
+            vec![Box::new(TextPatt(String::from("import"))), Box::new(ExprPattern)];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let path = params.pop().unwrap();
+            let Node::Str(path) = path.node() else {
+                panic!("import takes a path in quotes, found {}", path.get_name())
+            };
+            Box::new(Self::new(path.0.clone(), false))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+    pub fn get_short_builder() -> ExprBuilder {
+        let patterns: Vec<Box<dyn SimplePattern>> =
+            vec![Box::new(TextPatt(String::from("use"))), Box::new(NamePatt)];
+        let constructor: ExprConstr = Box::new(move |mut params, _| {
+            let name = params.pop().unwrap();
+            Box::new(Self::new(format!("{}.src", name.get_name()), true))
+        });
+        ExprBuilder::new(patterns, constructor)
+    }
+    //the name the functions of the module are called with
+    pub fn module_name(&self) -> String {
+        let file = self.path.rsplit('/').next().unwrap_or(&self.path);
+        file.strip_suffix(".src").unwrap_or(file).to_string()
+    }
+}
+impl Expression for Import {
+    fn get_name(&self) -> String {
+        String::from("import")
+    }
+    fn node(&self) -> Node<'_> {
+        Node::Import(self)
+    }
+    fn node_mut(&mut self) -> NodeMut<'_> {
+        NodeMut::Import(self)
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
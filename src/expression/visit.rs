@@ -0,0 +1,288 @@
+use super::{
+    statements::{
+        ArrayLit, Bool, Field, FieldAssign, FuncDecl, FunctionCall, IfBlock, Import, Index,
+        IndexAssign, Number, Return, Str, StructDecl, StructLit, TwoSideOp, Var, VarDecl,
+        WhileBlock,
+    },
+    CodeBlock, Expression, Node, NodeMut,
+};
+
+//read only traversal, overwrite the visit_* methods a pass cares about
+//and call the matching walk_* function to continue into the children
+pub trait Visitor {
+    fn visit_expr(&mut self, expr: &dyn Expression) {
+        walk_expr(self, expr)
+    }
+    fn visit_block(&mut self, block: &CodeBlock) {
+        walk_block(self, block)
+    }
+    fn visit_if(&mut self, if_block: &IfBlock) {
+        walk_if(self, if_block)
+    }
+    fn visit_while(&mut self, while_block: &WhileBlock) {
+        walk_while(self, while_block)
+    }
+    fn visit_bin_op(&mut self, op: &TwoSideOp) {
+        walk_bin_op(self, op)
+    }
+    fn visit_number(&mut self, _number: &Number) {}
+    fn visit_str(&mut self, _string: &Str) {}
+    fn visit_bool(&mut self, _value: &Bool) {}
+    fn visit_var(&mut self, _var: &Var) {}
+    fn visit_call(&mut self, call: &FunctionCall) {
+        walk_call(self, call)
+    }
+    fn visit_func_decl(&mut self, decl: &FuncDecl) {
+        walk_func_decl(self, decl)
+    }
+    fn visit_var_decl(&mut self, decl: &VarDecl) {
+        walk_var_decl(self, decl)
+    }
+    fn visit_return(&mut self, ret: &Return) {
+        walk_return(self, ret)
+    }
+    fn visit_array(&mut self, array: &ArrayLit) {
+        walk_array(self, array)
+    }
+    fn visit_index(&mut self, index: &Index) {
+        walk_index(self, index)
+    }
+    fn visit_index_assign(&mut self, assign: &IndexAssign) {
+        walk_index_assign(self, assign)
+    }
+    fn visit_struct_decl(&mut self, _decl: &StructDecl) {}
+    fn visit_struct_lit(&mut self, lit: &StructLit) {
+        walk_struct_lit(self, lit)
+    }
+    fn visit_field(&mut self, field: &Field) {
+        walk_field(self, field)
+    }
+    fn visit_field_assign(&mut self, assign: &FieldAssign) {
+        walk_field_assign(self, assign)
+    }
+    fn visit_import(&mut self, _import: &Import) {}
+}
+pub fn walk_expr<V: Visitor + ?Sized>(v: &mut V, expr: &dyn Expression) {
+    match expr.node() {
+        Node::Block(block) => v.visit_block(block),
+        Node::If(if_block) => v.visit_if(if_block),
+        Node::While(while_block) => v.visit_while(while_block),
+        Node::BinOp(op) => v.visit_bin_op(op),
+        Node::Number(number) => v.visit_number(number),
+        Node::Str(string) => v.visit_str(string),
+        Node::Bool(value) => v.visit_bool(value),
+        Node::Var(var) => v.visit_var(var),
+        Node::Call(call) => v.visit_call(call),
+        Node::FuncDecl(decl) => v.visit_func_decl(decl),
+        Node::VarDecl(decl) => v.visit_var_decl(decl),
+        Node::Return(ret) => v.visit_return(ret),
+        Node::Array(array) => v.visit_array(array),
+        Node::Index(index) => v.visit_index(index),
+        Node::IndexAssign(assign) => v.visit_index_assign(assign),
+        Node::StructDecl(decl) => v.visit_struct_decl(decl),
+        Node::StructLit(lit) => v.visit_struct_lit(lit),
+        Node::Field(field) => v.visit_field(field),
+        Node::FieldAssign(assign) => v.visit_field_assign(assign),
+        Node::Import(import) => v.visit_import(import),
+    }
+}
+pub fn walk_block<V: Visitor + ?Sized>(v: &mut V, block: &CodeBlock) {
+    for line in &block.lines {
+        v.visit_expr(line.as_ref());
+    }
+}
+pub fn walk_if<V: Visitor + ?Sized>(v: &mut V, if_block: &IfBlock) {
+    v.visit_expr(if_block.cond.as_ref());
+    v.visit_expr(if_block.code.as_ref());
+}
+pub fn walk_while<V: Visitor + ?Sized>(v: &mut V, while_block: &WhileBlock) {
+    v.visit_expr(while_block.cond.as_ref());
+    v.visit_expr(while_block.code.as_ref());
+}
+pub fn walk_bin_op<V: Visitor + ?Sized>(v: &mut V, op: &TwoSideOp) {
+    v.visit_expr(op.values.0.as_ref());
+    v.visit_expr(op.values.1.as_ref());
+}
+pub fn walk_call<V: Visitor + ?Sized>(v: &mut V, call: &FunctionCall) {
+    for arg in &call.args {
+        v.visit_expr(arg.as_ref());
+    }
+}
+pub fn walk_func_decl<V: Visitor + ?Sized>(v: &mut V, decl: &FuncDecl) {
+    v.visit_expr(decl.body.as_ref());
+}
+pub fn walk_var_decl<V: Visitor + ?Sized>(v: &mut V, decl: &VarDecl) {
+    v.visit_expr(decl.value.as_ref());
+}
+pub fn walk_return<V: Visitor + ?Sized>(v: &mut V, ret: &Return) {
+    v.visit_expr(ret.value.as_ref());
+}
+pub fn walk_array<V: Visitor + ?Sized>(v: &mut V, array: &ArrayLit) {
+    for value in &array.values {
+        v.visit_expr(value.as_ref());
+    }
+    if let Some(len) = &array.len {
+        v.visit_expr(len.as_ref());
+    }
+}
+pub fn walk_index<V: Visitor + ?Sized>(v: &mut V, index: &Index) {
+    v.visit_expr(index.array.as_ref());
+    v.visit_expr(index.index.as_ref());
+}
+pub fn walk_index_assign<V: Visitor + ?Sized>(v: &mut V, assign: &IndexAssign) {
+    v.visit_expr(assign.array.as_ref());
+    v.visit_expr(assign.index.as_ref());
+    v.visit_expr(assign.value.as_ref());
+}
+pub fn walk_struct_lit<V: Visitor + ?Sized>(v: &mut V, lit: &StructLit) {
+    for value in &lit.values {
+        v.visit_expr(value.as_ref());
+    }
+}
+pub fn walk_field<V: Visitor + ?Sized>(v: &mut V, field: &Field) {
+    v.visit_expr(field.object.as_ref());
+}
+pub fn walk_field_assign<V: Visitor + ?Sized>(v: &mut V, assign: &FieldAssign) {
+    v.visit_expr(assign.object.as_ref());
+    v.visit_expr(assign.value.as_ref());
+}
+
+//mutating traversal, works like a fold: visit_expr gets the box itself,
+//so a pass can replace a whole node (e.g. `*expr = Box::new(Number(..))`)
+pub trait MutVisitor {
+    fn visit_expr(&mut self, expr: &mut Box<dyn Expression>) {
+        walk_expr_mut(self, expr)
+    }
+    fn visit_block(&mut self, block: &mut CodeBlock) {
+        walk_block_mut(self, block)
+    }
+    fn visit_if(&mut self, if_block: &mut IfBlock) {
+        walk_if_mut(self, if_block)
+    }
+    fn visit_while(&mut self, while_block: &mut WhileBlock) {
+        walk_while_mut(self, while_block)
+    }
+    fn visit_bin_op(&mut self, op: &mut TwoSideOp) {
+        walk_bin_op_mut(self, op)
+    }
+    fn visit_number(&mut self, _number: &mut Number) {}
+    fn visit_str(&mut self, _string: &mut Str) {}
+    fn visit_bool(&mut self, _value: &mut Bool) {}
+    fn visit_var(&mut self, _var: &mut Var) {}
+    fn visit_call(&mut self, call: &mut FunctionCall) {
+        walk_call_mut(self, call)
+    }
+    fn visit_func_decl(&mut self, decl: &mut FuncDecl) {
+        walk_func_decl_mut(self, decl)
+    }
+    fn visit_var_decl(&mut self, decl: &mut VarDecl) {
+        walk_var_decl_mut(self, decl)
+    }
+    fn visit_return(&mut self, ret: &mut Return) {
+        walk_return_mut(self, ret)
+    }
+    fn visit_array(&mut self, array: &mut ArrayLit) {
+        walk_array_mut(self, array)
+    }
+    fn visit_index(&mut self, index: &mut Index) {
+        walk_index_mut(self, index)
+    }
+    fn visit_index_assign(&mut self, assign: &mut IndexAssign) {
+        walk_index_assign_mut(self, assign)
+    }
+    fn visit_struct_decl(&mut self, _decl: &mut StructDecl) {}
+    fn visit_struct_lit(&mut self, lit: &mut StructLit) {
+        walk_struct_lit_mut(self, lit)
+    }
+    fn visit_field(&mut self, field: &mut Field) {
+        walk_field_mut(self, field)
+    }
+    fn visit_field_assign(&mut self, assign: &mut FieldAssign) {
+        walk_field_assign_mut(self, assign)
+    }
+    fn visit_import(&mut self, _import: &mut Import) {}
+}
+pub fn walk_expr_mut<V: MutVisitor + ?Sized>(v: &mut V, expr: &mut Box<dyn Expression>) {
+    match expr.node_mut() {
+        NodeMut::Block(block) => v.visit_block(block),
+        NodeMut::If(if_block) => v.visit_if(if_block),
+        NodeMut::While(while_block) => v.visit_while(while_block),
+        NodeMut::BinOp(op) => v.visit_bin_op(op),
+        NodeMut::Number(number) => v.visit_number(number),
+        NodeMut::Str(string) => v.visit_str(string),
+        NodeMut::Bool(value) => v.visit_bool(value),
+        NodeMut::Var(var) => v.visit_var(var),
+        NodeMut::Call(call) => v.visit_call(call),
+        NodeMut::FuncDecl(decl) => v.visit_func_decl(decl),
+        NodeMut::VarDecl(decl) => v.visit_var_decl(decl),
+        NodeMut::Return(ret) => v.visit_return(ret),
+        NodeMut::Array(array) => v.visit_array(array),
+        NodeMut::Index(index) => v.visit_index(index),
+        NodeMut::IndexAssign(assign) => v.visit_index_assign(assign),
+        NodeMut::StructDecl(decl) => v.visit_struct_decl(decl),
+        NodeMut::StructLit(lit) => v.visit_struct_lit(lit),
+        NodeMut::Field(field) => v.visit_field(field),
+        NodeMut::FieldAssign(assign) => v.visit_field_assign(assign),
+        NodeMut::Import(import) => v.visit_import(import),
+    }
+}
+pub fn walk_block_mut<V: MutVisitor + ?Sized>(v: &mut V, block: &mut CodeBlock) {
+    for line in &mut block.lines {
+        v.visit_expr(line);
+    }
+}
+pub fn walk_if_mut<V: MutVisitor + ?Sized>(v: &mut V, if_block: &mut IfBlock) {
+    v.visit_expr(&mut if_block.cond);
+    v.visit_expr(&mut if_block.code);
+}
+pub fn walk_while_mut<V: MutVisitor + ?Sized>(v: &mut V, while_block: &mut WhileBlock) {
+    v.visit_expr(&mut while_block.cond);
+    v.visit_expr(&mut while_block.code);
+}
+pub fn walk_bin_op_mut<V: MutVisitor + ?Sized>(v: &mut V, op: &mut TwoSideOp) {
+    v.visit_expr(&mut op.values.0);
+    v.visit_expr(&mut op.values.1);
+}
+pub fn walk_call_mut<V: MutVisitor + ?Sized>(v: &mut V, call: &mut FunctionCall) {
+    for arg in &mut call.args {
+        v.visit_expr(arg);
+    }
+}
+pub fn walk_func_decl_mut<V: MutVisitor + ?Sized>(v: &mut V, decl: &mut FuncDecl) {
+    v.visit_expr(&mut decl.body);
+}
+pub fn walk_var_decl_mut<V: MutVisitor + ?Sized>(v: &mut V, decl: &mut VarDecl) {
+    v.visit_expr(&mut decl.value);
+}
+pub fn walk_return_mut<V: MutVisitor + ?Sized>(v: &mut V, ret: &mut Return) {
+    v.visit_expr(&mut ret.value);
+}
+pub fn walk_array_mut<V: MutVisitor + ?Sized>(v: &mut V, array: &mut ArrayLit) {
+    for value in &mut array.values {
+        v.visit_expr(value);
+    }
+    if let Some(len) = &mut array.len {
+        v.visit_expr(len);
+    }
+}
+pub fn walk_index_mut<V: MutVisitor + ?Sized>(v: &mut V, index: &mut Index) {
+    v.visit_expr(&mut index.array);
+    v.visit_expr(&mut index.index);
+}
+pub fn walk_index_assign_mut<V: MutVisitor + ?Sized>(v: &mut V, assign: &mut IndexAssign) {
+    v.visit_expr(&mut assign.array);
+    v.visit_expr(&mut assign.index);
+    v.visit_expr(&mut assign.value);
+}
+pub fn walk_struct_lit_mut<V: MutVisitor + ?Sized>(v: &mut V, lit: &mut StructLit) {
+    for value in &mut lit.values {
+        v.visit_expr(value);
+    }
+}
+pub fn walk_field_mut<V: MutVisitor + ?Sized>(v: &mut V, field: &mut Field) {
+    v.visit_expr(&mut field.object);
+}
+pub fn walk_field_assign_mut<V: MutVisitor + ?Sized>(v: &mut V, assign: &mut FieldAssign) {
+    v.visit_expr(&mut assign.object);
+    v.visit_expr(&mut assign.value);
+}
@@ -0,0 +1,255 @@
+use crate::{
+    expression::{
+        statements::{
+            ArrayLit, Bool, Field, FieldAssign, FuncDecl, FunctionCall, IfBlock, Import,
+            Index, IndexAssign, Number, Return, Str, StructDecl, StructLit, TwoSideOp, Var, VarDecl,
+            WhileBlock,
+        },
+        visit::Visitor,
+        BlockType, CodeBlock, Expression, Node,
+    },
+    lexer::escape_str,
+};
+
+const INDENT: &str = "    ";
+
+//turns a parsed program back into canonical source
+pub fn format(program: &CodeBlock) -> String {
+    let mut formatter = Formatter::default();
+    formatter.write_lines(&program.lines);
+    formatter.out
+}
+
+#[derive(Default)]
+struct Formatter {
+    out: String,
+    level: usize,
+}
+impl Formatter {
+    fn write_lines(&mut self, lines: &[Box<dyn Expression>]) {
+        for line in lines {
+            self.out.push_str(&INDENT.repeat(self.level));
+            self.visit_expr(line.as_ref());
+            //statements ending in a curly block don't need a semicolon
+            if !ends_with_block(line.as_ref()) {
+                self.out.push(';');
+            }
+            self.out.push('\n');
+        }
+    }
+    fn write_list(&mut self, items: &[Box<dyn Expression>]) {
+        self.out.push('(');
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.visit_expr(item.as_ref());
+        }
+        self.out.push(')');
+    }
+}
+fn ends_with_block(line: &dyn Expression) -> bool {
+    match line.node() {
+        Node::Block(block) => block.block_type == BlockType::Curl,
+        Node::If(_) | Node::While(_) | Node::FuncDecl(_) | Node::StructDecl(_) => true,
+        _ => false,
+    }
+}
+impl Visitor for Formatter {
+    fn visit_block(&mut self, block: &CodeBlock) {
+        match block.block_type {
+            BlockType::Brack => self.write_list(&block.lines),
+            BlockType::Curl if block.lines.is_empty() => self.out.push_str("{}"),
+            BlockType::Curl => {
+                self.out.push_str("{\n");
+                self.level += 1;
+                self.write_lines(&block.lines);
+                self.level -= 1;
+                self.out.push_str(&INDENT.repeat(self.level));
+                self.out.push('}');
+            }
+        }
+    }
+    fn visit_if(&mut self, if_block: &IfBlock) {
+        self.out.push_str("if ");
+        self.visit_expr(if_block.cond.as_ref());
+        self.out.push(' ');
+        self.visit_expr(if_block.code.as_ref());
+    }
+    fn visit_while(&mut self, while_block: &WhileBlock) {
+        self.out.push_str("while ");
+        self.visit_expr(while_block.cond.as_ref());
+        self.out.push(' ');
+        self.visit_expr(while_block.code.as_ref());
+    }
+    fn visit_bin_op(&mut self, op: &TwoSideOp) {
+        self.visit_expr(op.values.0.as_ref());
+        self.out.push_str(&format!(" {} ", op.sign));
+        self.visit_expr(op.values.1.as_ref());
+    }
+    fn visit_number(&mut self, number: &Number) {
+        self.out.push_str(&number.0);
+    }
+    fn visit_str(&mut self, string: &Str) {
+        self.out.push_str(&escape_str(&string.0));
+    }
+    fn visit_bool(&mut self, value: &Bool) {
+        self.out.push_str(&value.0.to_string());
+    }
+    fn visit_var(&mut self, var: &Var) {
+        self.out.push_str(&var.name);
+        if let Some(ty) = &var.ty {
+            self.out.push_str(&format!(": {ty}"));
+        }
+    }
+    fn visit_call(&mut self, call: &FunctionCall) {
+        self.out.push_str(&call.func.get_name());
+        self.write_list(&call.args);
+    }
+    fn visit_func_decl(&mut self, decl: &FuncDecl) {
+        let args = decl
+            .args
+            .iter()
+            .map(|arg| match &arg.ty {
+                Some(ty) => format!("{}: {ty}", arg.name),
+                None => arg.name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        if decl.inline {
+            self.out.push_str("inline ");
+        }
+        self.out.push_str(&format!("def {}({}) ", decl.name, args));
+        if let Some(ty) = &decl.ret {
+            self.out.push_str(&format!("-> {ty} "));
+        }
+        self.visit_expr(decl.body.as_ref());
+    }
+    fn visit_var_decl(&mut self, decl: &VarDecl) {
+        match &decl.ty {
+            Some(ty) => self.out.push_str(&format!("{}: {ty} sei ", decl.name)),
+            None => self.out.push_str(&format!("{} sei ", decl.name)),
+        }
+        self.visit_expr(decl.value.as_ref());
+    }
+    fn visit_return(&mut self, ret: &Return) {
+        self.out.push_str("return ");
+        self.visit_expr(ret.value.as_ref());
+    }
+    fn visit_array(&mut self, array: &ArrayLit) {
+        self.out.push('[');
+        for (i, value) in array.values.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.visit_expr(value.as_ref());
+        }
+        if let Some(len) = &array.len {
+            self.out.push_str("; ");
+            self.visit_expr(len.as_ref());
+        }
+        self.out.push(']');
+    }
+    fn visit_index(&mut self, index: &Index) {
+        self.visit_expr(index.array.as_ref());
+        self.out.push('[');
+        self.visit_expr(index.index.as_ref());
+        self.out.push(']');
+    }
+    fn visit_index_assign(&mut self, assign: &IndexAssign) {
+        self.visit_expr(assign.array.as_ref());
+        self.out.push('[');
+        self.visit_expr(assign.index.as_ref());
+        self.out.push_str("] sei ");
+        self.visit_expr(assign.value.as_ref());
+    }
+    fn visit_struct_decl(&mut self, decl: &StructDecl) {
+        self.out.push_str(&format!("struct {} {{ ", decl.name));
+        for (i, field) in decl.fields.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.visit_var(field);
+        }
+        self.out.push_str(" }");
+    }
+    fn visit_struct_lit(&mut self, lit: &StructLit) {
+        self.out.push_str(&format!("{} {{ ", lit.name));
+        for (i, value) in lit.values.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.visit_expr(value.as_ref());
+        }
+        self.out.push_str(" }");
+    }
+    fn visit_field(&mut self, field: &Field) {
+        self.visit_expr(field.object.as_ref());
+        self.out.push_str(&format!(".{}", field.name));
+    }
+    fn visit_field_assign(&mut self, assign: &FieldAssign) {
+        self.visit_expr(assign.object.as_ref());
+        self.out.push_str(&format!(".{} sei ", assign.name));
+        self.visit_expr(assign.value.as_ref());
+    }
+    fn visit_import(&mut self, import: &Import) {
+        if import.short {
+            self.out.push_str(&format!("use {}", import.module_name()));
+        } else {
+            self.out.push_str(&format!("import {}", escape_str(&import.path)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        expression::visit::{walk_expr, Visitor},
+        parser::{default_builders, parse},
+    };
+
+    //records the kind and name of every node, used to compare two trees
+    #[derive(Default)]
+    struct Shape(Vec<String>);
+    impl Visitor for Shape {
+        fn visit_expr(&mut self, expr: &dyn Expression) {
+            self.0.push(format!("{:?}:{}", std::mem::discriminant(&expr.node()), expr.get_name()));
+            walk_expr(self, expr)
+        }
+    }
+    fn shape(block: &CodeBlock) -> Vec<String> {
+        let mut shape = Shape::default();
+        shape.visit_block(block);
+        shape.0
+    }
+
+    #[test]
+    fn formats_canonically() {
+        let s = "x sei 5;def a(v){x sei 3;v sei x+v;print(v)}\na(3);if(x<2){}print(x+2)";
+        let ast = parse(s.to_string(), &default_builders());
+        assert_eq!(
+            format(&ast),
+            "x sei 5;\ndef a(v) {\n    x sei 3;\n    v sei x + v;\n    print(v);\n}\na(3);\nif (x < 2) {}\nprint(x + 2);\n"
+        );
+    }
+    #[test]
+    fn round_trip_keeps_ast() {
+        let s = "
+        import \"lib/math.src\"; use io;
+        n sei 0;
+        while (n < 10) { if ((n + 1) * 2 < 7) { print(n * (2 + 3)) } n sei n + 1 }
+        def f(a, b) { a * b + 1 }
+        def g(a: int, s: string) -> bool { t: bool sei a < 2; t }
+        a sei [1, 2, f(1, 2)]; b sei [[0; 3], a]; b[1][a[0] + 1] sei a[2] * 2
+        struct Pair { l: int, r } p sei Pair { 1, [Pair { 2, a }] }; p.r[0].l sei p.l + 1
+        print(f(2, 3)); print(math.gcd(4, 6))
+        ";
+        let builders = default_builders();
+        let ast = parse(s.to_string(), &builders);
+        let formatted = format(&ast);
+        let reparsed = parse(formatted.clone(), &builders);
+        assert_eq!(shape(&ast), shape(&reparsed));
+        assert_eq!(formatted, format(&reparsed));
+    }
+}
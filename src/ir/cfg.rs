@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use super::{ssa, Function, Instr, Label, Module};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub label: Label,
+    //without the label, the terminator is the last instruction
+    pub instrs: Vec<Instr>,
+    //if the block doesn't end in a jump or return the first successor is the fall through
+    pub succs: Vec<usize>,
+    pub preds: Vec<usize>,
+}
+impl BasicBlock {
+    fn new(label: Label) -> Self {
+        Self {
+            label,
+            instrs: vec![],
+            succs: vec![],
+            preds: vec![],
+        }
+    }
+    pub fn terminator(&self) -> Option<&Instr> {
+        self.instrs.last().filter(|instr| instr.is_terminator())
+    }
+    //new instructions go in front of the terminator, so they run on every way out
+    pub fn insert_before_terminator(&mut self, instrs: Vec<Instr>) {
+        let at = self.instrs.len() - self.terminator().is_some() as usize;
+        self.instrs.splice(at..at, instrs);
+    }
+}
+
+//control flow graph of one function, block 0 is the entry
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+}
+impl Cfg {
+    //unreachable blocks are dropped
+    pub fn build(func: &Function) -> Self {
+        let mut blocks = vec![];
+        //the entry can't have predecessors, a loop right at the start gets a block in front
+        if let Some(Instr::Label(_)) = func.body.first() {
+            blocks.push(BasicBlock::new(format!("_{}_b0", func.name)));
+        }
+        let mut current: Option<BasicBlock> = None;
+        for instr in &func.body {
+            if let Instr::Label(label) = instr {
+                blocks.extend(current.take());
+                current = Some(BasicBlock::new(label.clone()));
+                continue;
+            }
+            let block = current
+                .get_or_insert_with(|| BasicBlock::new(format!("_{}_b{}", func.name, blocks.len())));
+            block.instrs.push(instr.clone());
+            if instr.is_terminator() {
+                blocks.extend(current.take());
+            }
+        }
+        blocks.extend(current);
+        if blocks.is_empty() {
+            blocks.push(BasicBlock::new(format!("_{}_b0", func.name)));
+        }
+        let index = label_index(&blocks);
+        for i in 0..blocks.len() {
+            let next = (i + 1 < blocks.len()).then_some(i + 1);
+            blocks[i].succs = match blocks[i].terminator() {
+                Some(Instr::Jump(target)) => vec![index[target]],
+                Some(Instr::Branch { if_false, .. }) => next.into_iter().chain([index[if_false]]).collect(),
+                Some(Instr::Return(_)) => vec![],
+                _ => next.into_iter().collect(),
+            };
+        }
+        let mut cfg = Self { blocks };
+        cfg.remove_unreachable();
+        cfg.link_preds();
+        cfg
+    }
+    fn remove_unreachable(&mut self) {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut stack = vec![0];
+        while let Some(b) = stack.pop() {
+            if !reachable[b] {
+                reachable[b] = true;
+                stack.extend(&self.blocks[b].succs);
+            }
+        }
+        let mut new_index = vec![0; self.blocks.len()];
+        let mut next = 0;
+        for (b, keep) in reachable.iter().enumerate() {
+            new_index[b] = next;
+            next += *keep as usize;
+        }
+        let blocks = std::mem::take(&mut self.blocks);
+        self.blocks = blocks
+            .into_iter()
+            .zip(reachable)
+            .filter(|(_, keep)| *keep)
+            .map(|(mut block, _)| {
+                block.succs.iter_mut().for_each(|s| *s = new_index[*s]);
+                block
+            })
+            .collect();
+    }
+    fn link_preds(&mut self) {
+        for block in &mut self.blocks {
+            block.preds.clear();
+        }
+        for b in 0..self.blocks.len() {
+            for s in self.blocks[b].succs.clone() {
+                if !self.blocks[s].preds.contains(&b) {
+                    self.blocks[s].preds.push(b);
+                }
+            }
+        }
+    }
+    //blocks in reverse postorder, every block comes before its successors apart from back edges
+    pub fn reverse_postorder(&self) -> Vec<usize> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut order = vec![];
+        //(block, index of the next successor to look at)
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        while let Some((b, i)) = stack.pop() {
+            match self.blocks[b].succs.get(i) {
+                Some(&s) => {
+                    stack.push((b, i + 1));
+                    if !visited[s] {
+                        visited[s] = true;
+                        stack.push((s, 0));
+                    }
+                }
+                None => order.push(b),
+            }
+        }
+        order.reverse();
+        order
+    }
+    //puts a new block on the edge from `from` to `to` and returns it
+    pub fn split_edge(&mut self, from: usize, to: usize) -> usize {
+        let target = self.blocks[to].label.clone();
+        let label = format!("{}_{}", self.blocks[from].label, target);
+        let new = self.blocks.len();
+        let mut block = BasicBlock::new(label.clone());
+        block.instrs.push(Instr::Jump(target.clone()));
+        block.succs = vec![to];
+        block.preds = vec![from];
+        self.blocks.push(block);
+        let from_block = &mut self.blocks[from];
+        from_block.succs.iter_mut().filter(|s| **s == to).for_each(|s| *s = new);
+        if let Some(Instr::Branch { if_false, .. }) = from_block.instrs.last_mut() {
+            if *if_false == target {
+                *if_false = label;
+            }
+        }
+        self.blocks[to].preds.iter_mut().filter(|p| **p == from).for_each(|p| *p = new);
+        new
+    }
+    //back to a linear body, jumps are added where a block doesn't fall through anymore
+    pub fn into_body(self) -> Vec<Instr> {
+        let mut body = vec![];
+        for (i, block) in self.blocks.iter().enumerate() {
+            body.push(Instr::Label(block.label.clone()));
+            body.extend(block.instrs.iter().cloned());
+            let falls_through = matches!(block.terminator(), None | Some(Instr::Branch { .. }));
+            match block.succs.first() {
+                Some(&next) if falls_through && next != i + 1 => {
+                    body.push(Instr::Jump(self.blocks[next].label.clone()))
+                }
+                _ => (),
+            }
+        }
+        body
+    }
+    //one cluster per function, edge labels only where a branch splits
+    pub fn to_dot(&self, func: &Function) -> String {
+        let mut out = format!("    subgraph \"cluster_{}\" {{\n", func.name);
+        out += &format!("        label=\"{}\";\n", escape(&func.name));
+        for (i, block) in self.blocks.iter().enumerate() {
+            let mut text = format!("{}:\\l", escape(&block.label));
+            for instr in &block.instrs {
+                text += &format!("{}\\l", escape(&func.fmt_instr(instr)));
+            }
+            out += &format!("        \"{}_{i}\" [label=\"{text}\"];\n", func.name);
+        }
+        for (i, block) in self.blocks.iter().enumerate() {
+            let branches = matches!(block.terminator(), Some(Instr::Branch { .. }));
+            for (n, s) in block.succs.iter().enumerate() {
+                let attrs = match (branches, n) {
+                    (false, _) => String::new(),
+                    (true, 0) => " [label=\"true\"]".to_string(),
+                    (true, _) => " [label=\"false\"]".to_string(),
+                };
+                out += &format!("        \"{0}_{i}\" -> \"{0}_{s}\"{attrs};\n", func.name);
+            }
+        }
+        out + "    }\n"
+    }
+}
+fn label_index(blocks: &[BasicBlock]) -> HashMap<Label, usize> {
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| (block.label.clone(), i))
+        .collect()
+}
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+//graphviz of all functions in ssa form, for --emit cfg
+pub fn dot(module: &Module) -> String {
+    let mut out = String::from("digraph program {\n    node [shape=box fontname=monospace];\n");
+    for func in module.all_functions() {
+        let mut func = func.clone();
+        let mut cfg = Cfg::build(&func);
+        ssa::construct(&mut func, &mut cfg);
+        out += &cfg.to_dot(&func);
+    }
+    out + "}\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lower_source;
+
+    #[test]
+    fn splits_loops_into_blocks() {
+        let module = lower_source("i sei 0; while (i < 3) { i sei i + 1 } print(i)").unwrap();
+        let cfg = Cfg::build(&module.main);
+        let edges = cfg
+            .blocks
+            .iter()
+            .map(|block| (block.label.as_str(), block.succs.clone(), block.preds.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![
+                ("_main_b0", vec![1], vec![]),
+                ("_L0", vec![2, 3], vec![0, 2]),
+                ("_main_b2", vec![1], vec![1]),
+                ("_L1", vec![], vec![1]),
+            ]
+        );
+        assert_eq!(cfg.reverse_postorder(), vec![0, 1, 3, 2]);
+    }
+}
@@ -0,0 +1,362 @@
+use crate::mips::{
+    instr::{DataItem, DataValue, FReg, Instr as Mips, Item, Program, Reg},
+    Code, ENTRY,
+};
+
+use super::{
+    regalloc::{allocate, Allocation, Loc},
+    BinOp, Callee, Function, Instr, Module, Operand, Place,
+};
+
+//frame layout, $fp points at the saved $fp:
+//  8+4*i($fp)   argument i, pushed by the caller
+//  4($fp)       saved $ra
+//  0($fp)       saved $fp of the caller
+//  -4*(k+1)     spill slot k, after them the saved $s registers and the arrays
+pub fn gen_program(module: &Module) -> Program {
+    let mut text = gen_function(&module.main, ENTRY, true);
+    for func in &module.functions {
+        text.extend(gen_function(func, &func.name, false));
+    }
+    let mut runtime = module
+        .all_functions()
+        .flat_map(|func| &func.body)
+        .filter_map(|instr| match instr {
+            Instr::Call { callee: Callee::Runtime(runtime), .. } => Some(*runtime),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    runtime.sort();
+    runtime.dedup();
+    for routine in runtime {
+        text.extend(routine.code());
+    }
+    let mut program = Program::new(text);
+    for (n, string) in module.strings.iter().enumerate() {
+        program.data.push(DataItem {
+            label: str_label(n),
+            value: DataValue::Asciiz(string.clone()),
+        });
+    }
+    for (n, x) in module.floats.iter().enumerate() {
+        program.data.push(DataItem {
+            label: float_label(n),
+            value: DataValue::Float(vec![*x]),
+        });
+    }
+    program
+}
+fn str_label(n: usize) -> String {
+    format!("_str{n}")
+}
+fn float_label(n: usize) -> String {
+    format!("_flt{n}")
+}
+
+fn gen_function(func: &Function, label: &str, is_main: bool) -> Code {
+    let alloc = allocate(func);
+    let gen = FuncGen {
+        alloc,
+        ret_label: format!("{label}_ret"),
+        is_main,
+        frame_words: func.frame_words,
+    };
+    let mut code = vec![Item::Label(label.to_string())];
+    code.extend(gen.prologue());
+    for (i, param) in func.params.iter().enumerate() {
+        //params that are never read have no location
+        if gen.alloc.locs.contains_key(&Place::Var(*param)) {
+            code.push(Mips::Lw(Reg::T0, 8 + 4 * i as i32, Reg::Fp).into());
+            code.extend(gen.store(Reg::T0, Place::Var(*param)));
+        }
+    }
+    for (i, instr) in func.body.iter().enumerate() {
+        code.extend(gen.gen_instr(i, instr));
+    }
+    code.extend(gen.epilogue());
+    code
+}
+
+struct FuncGen {
+    alloc: Allocation,
+    ret_label: String,
+    is_main: bool,
+    frame_words: usize,
+}
+impl FuncGen {
+    //main never returns, so it doesn't have to keep the $s registers
+    fn saved_regs(&self) -> &[Reg] {
+        if self.is_main {
+            &[]
+        } else {
+            &self.alloc.saved_regs
+        }
+    }
+    fn frame_size(&self) -> i32 {
+        4 * (self.alloc.n_spills + self.saved_regs().len() + self.frame_words) as i32
+    }
+    fn slot_offset(slot: usize) -> i32 {
+        -4 * (slot as i32 + 1)
+    }
+    //the arrays are at the bottom of the frame, their words go upwards
+    fn array_offset(&self, slot: usize) -> i32 {
+        -self.frame_size() + 4 * slot as i32
+    }
+    //dst = src + value, big arrays make offsets that don't fit into an immediate
+    fn add_imm(&self, dst: Reg, src: Reg, value: i32) -> Code {
+        if i16::try_from(value).is_ok() {
+            return vec![Mips::Addi(dst, src, value).into()];
+        }
+        let mut code = self.load(Reg::T1, &Operand::Const(value));
+        code.push(Mips::Add(dst, src, Reg::T1).into());
+        code
+    }
+    fn prologue(&self) -> Code {
+        let mut code: Code = vec![
+            Mips::Addi(Reg::Sp, Reg::Sp, -8).into(),
+            Mips::Sw(Reg::Ra, 4, Reg::Sp).into(),
+            Mips::Sw(Reg::Fp, 0, Reg::Sp).into(),
+            Mips::Add(Reg::Fp, Reg::Sp, Reg::Zero).into(),
+        ];
+        code.extend(self.add_imm(Reg::Sp, Reg::Sp, -self.frame_size()));
+        for (i, reg) in self.saved_regs().iter().enumerate() {
+            let offset = Self::slot_offset(self.alloc.n_spills + i);
+            code.push(Mips::Sw(*reg, offset, Reg::Fp).into());
+        }
+        code
+    }
+    fn epilogue(&self) -> Code {
+        let mut code = vec![Item::Label(self.ret_label.clone())];
+        if self.is_main {
+            code.extend([
+                Mips::Addi(Reg::V0, Reg::Zero, 10).into(),
+                Mips::Syscall.into(),
+            ]);
+            return code;
+        }
+        for (i, reg) in self.saved_regs().iter().enumerate() {
+            let offset = Self::slot_offset(self.alloc.n_spills + i);
+            code.push(Mips::Lw(*reg, offset, Reg::Fp).into());
+        }
+        code.extend([
+            Mips::Add(Reg::Sp, Reg::Fp, Reg::Zero).into(),
+            Mips::Lw(Reg::Fp, 0, Reg::Sp).into(),
+            Mips::Lw(Reg::Ra, 4, Reg::Sp).into(),
+            Mips::Addi(Reg::Sp, Reg::Sp, 8).into(),
+            Mips::Jr(Reg::Ra).into(),
+        ]);
+        code
+    }
+    //puts the operand into `reg`
+    fn load(&self, reg: Reg, operand: &Operand) -> Code {
+        let instr = match operand {
+            Operand::Const(value) if i16::try_from(*value).is_ok() => {
+                Mips::Addi(reg, Reg::Zero, *value)
+            }
+            //too big for an immediate, the halves go in one after the other
+            Operand::Const(value) => {
+                let (upper, lower) = ((value >> 16) & 0xffff, value & 0xffff);
+                let mut code = vec![Mips::Lui(reg, upper).into()];
+                if lower != 0 {
+                    code.push(Mips::Ori(reg, reg, lower).into());
+                }
+                return code;
+            }
+            Operand::Str(n) => Mips::La(reg, str_label(*n)),
+            Operand::Float(n) => {
+                return vec![Mips::La(reg, float_label(*n)).into(), Mips::Lw(reg, 0, reg).into()]
+            }
+            Operand::Place(place) => match self.alloc.loc(*place) {
+                Loc::Reg(src) if src == reg => return vec![],
+                Loc::Reg(src) => Mips::Add(reg, src, Reg::Zero),
+                Loc::Stack(slot) => Mips::Lw(reg, Self::slot_offset(slot), Reg::Fp),
+            },
+        };
+        vec![instr.into()]
+    }
+    //register holding the operand, `scratch` is used if it isn't in one
+    fn operand_reg(&self, operand: &Operand, scratch: Reg) -> (Code, Reg) {
+        match operand {
+            Operand::Place(place) => match self.alloc.loc(*place) {
+                Loc::Reg(reg) => (vec![], reg),
+                Loc::Stack(_) => (self.load(scratch, operand), scratch),
+            },
+            Operand::Const(_) | Operand::Str(_) | Operand::Float(_) => {
+                (self.load(scratch, operand), scratch)
+            }
+        }
+    }
+    //register to compute the value of `place` in, followed by `store`
+    fn dst_reg(&self, place: Place, scratch: Reg) -> Reg {
+        match self.alloc.loc(place) {
+            Loc::Reg(reg) => reg,
+            Loc::Stack(_) => scratch,
+        }
+    }
+    fn store(&self, reg: Reg, place: Place) -> Code {
+        let instr = match self.alloc.loc(place) {
+            Loc::Reg(dst) if dst == reg => return vec![],
+            Loc::Reg(dst) => Mips::Add(dst, reg, Reg::Zero),
+            Loc::Stack(slot) => Mips::Sw(reg, Self::slot_offset(slot), Reg::Fp),
+        };
+        vec![instr.into()]
+    }
+    fn gen_instr(&self, i: usize, instr: &Instr) -> Code {
+        match instr {
+            Instr::Copy { dst, src } => {
+                let (load, reg) = match self.alloc.loc(*dst) {
+                    Loc::Reg(reg) => (self.load(reg, src), reg),
+                    Loc::Stack(_) => self.operand_reg(src, Reg::T0),
+                };
+                [load, self.store(reg, *dst)].concat()
+            }
+            Instr::BinOp {
+                dst,
+                op: BinOp::Shl,
+                lhs,
+                rhs: Operand::Const(shamt),
+            } => {
+                let (load, lhs) = self.operand_reg(lhs, Reg::T0);
+                let reg = self.dst_reg(*dst, Reg::T0);
+                [load, vec![Mips::Sll(reg, lhs, *shamt).into()], self.store(reg, *dst)].concat()
+            }
+            Instr::BinOp { dst, op, lhs, rhs } => {
+                let (load_lhs, lhs) = self.operand_reg(lhs, Reg::T0);
+                let (load_rhs, rhs) = self.operand_reg(rhs, Reg::T1);
+                let reg = self.dst_reg(*dst, Reg::T0);
+                [load_lhs, load_rhs, gen_bin_op(*op, reg, lhs, rhs), self.store(reg, *dst)].concat()
+            }
+            Instr::Label(label) => vec![Item::Label(label.clone())],
+            Instr::Jump(label) => vec![Mips::J(label.clone()).into()],
+            Instr::Branch { cond, if_false } => {
+                let (mut code, reg) = self.operand_reg(cond, Reg::T0);
+                code.push(Mips::Beqz(reg, if_false.clone()).into());
+                code
+            }
+            Instr::Call { dst, callee, args } => {
+                let mut code = match callee {
+                    Callee::Func(name) => self.gen_call(name, args, &self.alloc.call_saves[&i]),
+                    Callee::Syscall { code, .. } => gen_syscall(*code, args, |reg, arg| self.load(reg, arg)),
+                    Callee::ToFloat => self.gen_convert(Mips::CvtSW(F0, F0), &args[0]),
+                    Callee::ToInt => self.gen_convert(Mips::TruncWS(F0, F0), &args[0]),
+                    Callee::Runtime(runtime) => {
+                        self.gen_call(runtime.label(), args, &self.alloc.call_saves[&i])
+                    }
+                    Callee::Len => {
+                        let (mut code, reg) = self.operand_reg(&args[0], Reg::T0);
+                        code.push(Mips::Lw(Reg::V0, 0, reg).into());
+                        code
+                    }
+                };
+                if let Some(dst) = dst {
+                    code.extend(self.store(Reg::V0, *dst));
+                }
+                code
+            }
+            Instr::Return(value) => {
+                let mut code = self.load(Reg::V0, value);
+                code.push(Mips::J(self.ret_label.clone()).into());
+                code
+            }
+            Instr::FrameAddr { dst, slot } => {
+                let reg = self.dst_reg(*dst, Reg::T0);
+                let offset = self.array_offset(*slot);
+                [self.add_imm(reg, Reg::Fp, offset), self.store(reg, *dst)].concat()
+            }
+            Instr::Load { dst, base, offset } => {
+                let (load, base) = self.operand_reg(base, Reg::T0);
+                let reg = self.dst_reg(*dst, Reg::T0);
+                [load, vec![Mips::Lw(reg, *offset, base).into()], self.store(reg, *dst)].concat()
+            }
+            Instr::Store { base, offset, value } => {
+                let (load_base, base) = self.operand_reg(base, Reg::T0);
+                let (load_value, value) = self.operand_reg(value, Reg::T1);
+                [load_base, load_value, vec![Mips::Sw(value, *offset, base).into()]].concat()
+            }
+            Instr::Phi { .. } => unreachable!("phi nodes have to be removed before codegen"),
+        }
+    }
+    //the result ends up in $v0 like the one of a call
+    fn gen_convert(&self, convert: Mips, arg: &Operand) -> Code {
+        let (mut code, reg) = self.operand_reg(arg, Reg::T0);
+        let instrs = [Mips::Mtc1(reg, F0), convert, Mips::Mfc1(Reg::V0, F0)];
+        code.extend(instrs.map(Item::from));
+        code
+    }
+    //live $t registers are saved below the arguments, the callee finds the arguments
+    //above its saved registers
+    fn gen_call(&self, name: &str, args: &[Operand], saves: &[Reg]) -> Code {
+        let mut code = vec![];
+        if !saves.is_empty() {
+            code.push(Mips::Addi(Reg::Sp, Reg::Sp, -4 * saves.len() as i32).into());
+            for (i, reg) in saves.iter().enumerate() {
+                code.push(Mips::Sw(*reg, 4 * i as i32, Reg::Sp).into());
+            }
+        }
+        let size = 4 * args.len() as i32;
+        code.push(Mips::Addi(Reg::Sp, Reg::Sp, -size).into());
+        for (i, arg) in args.iter().enumerate() {
+            let (load, reg) = self.operand_reg(arg, Reg::T0);
+            code.extend(load);
+            code.push(Mips::Sw(reg, 4 * i as i32, Reg::Sp).into());
+        }
+        code.push(Mips::Jal(name.to_string()).into());
+        code.push(Mips::Addi(Reg::Sp, Reg::Sp, size).into());
+        if !saves.is_empty() {
+            for (i, reg) in saves.iter().enumerate() {
+                code.push(Mips::Lw(*reg, 4 * i as i32, Reg::Sp).into());
+            }
+            code.push(Mips::Addi(Reg::Sp, Reg::Sp, 4 * saves.len() as i32).into());
+        }
+        code
+    }
+}
+pub fn gen_bin_op(op: BinOp, dst: Reg, lhs: Reg, rhs: Reg) -> Code {
+    let instrs = match op {
+        BinOp::Add => vec![Mips::Add(dst, lhs, rhs)],
+        BinOp::Sub => vec![Mips::Sub(dst, lhs, rhs)],
+        BinOp::Mul => vec![Mips::Mult(lhs, rhs), Mips::Mflo(dst)],
+        BinOp::Div => vec![Mips::Div(lhs, rhs), Mips::Mflo(dst)],
+        BinOp::Rem => vec![Mips::Div(lhs, rhs), Mips::Mfhi(dst)],
+        BinOp::Lt => vec![Mips::Slt(dst, lhs, rhs)],
+        BinOp::Shl => vec![Mips::Sllv(dst, lhs, rhs)],
+        BinOp::Addu => vec![Mips::Addu(dst, lhs, rhs)],
+        BinOp::AddF => float_op(Mips::AddS(F0, F0, F2), dst, lhs, rhs),
+        BinOp::SubF => float_op(Mips::SubS(F0, F0, F2), dst, lhs, rhs),
+        BinOp::MulF => float_op(Mips::MulS(F0, F0, F2), dst, lhs, rhs),
+        BinOp::DivF => float_op(Mips::DivS(F0, F0, F2), dst, lhs, rhs),
+        BinOp::LtF => vec![
+            Mips::Mtc1(lhs, F0),
+            Mips::Mtc1(rhs, F2),
+            Mips::CLtS(F0, F2),
+            Mips::Addi(dst, Reg::Zero, 1),
+            Mips::Movf(dst, Reg::Zero),
+        ],
+    };
+    instrs.into_iter().map(Item::from).collect()
+}
+//floats live in the normal registers, the fpu only gets them for the operation
+const F0: FReg = FReg(0);
+const F2: FReg = FReg(2);
+fn float_op(op: Mips, dst: Reg, lhs: Reg, rhs: Reg) -> Vec<Mips> {
+    vec![Mips::Mtc1(lhs, F0), Mips::Mtc1(rhs, F2), op, Mips::Mfc1(dst, F0)]
+}
+const ARG_REGS: [Reg; 4] = [Reg::A0, Reg::A1, Reg::A2, Reg::A3];
+pub fn gen_syscall(code: i32, args: &[Operand], load: impl Fn(Reg, &Operand) -> Code) -> Code {
+    assert!(
+        args.len() <= ARG_REGS.len(),
+        "syscall with too many arguments"
+    );
+    let mut out = args
+        .iter()
+        .zip(ARG_REGS)
+        .flat_map(|(arg, reg)| load(reg, arg))
+        .collect::<Code>();
+    //printing a float takes it from $f12
+    if code == 2 {
+        out.push(Mips::Mtc1(Reg::A0, FReg(12)).into());
+    }
+    out.push(Mips::Addi(Reg::V0, Reg::Zero, code).into());
+    out.push(Mips::Syscall.into());
+    out
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeSet;
+
+use super::cfg::Cfg;
+
+//immediate dominators, after "A Simple, Fast Dominance Algorithm" by Cooper, Harvey and Kennedy
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dominators {
+    //the entry is its own immediate dominator
+    idom: Vec<usize>,
+}
+impl Dominators {
+    pub fn compute(cfg: &Cfg) -> Self {
+        let order = cfg.reverse_postorder();
+        let mut rpo_index = vec![usize::MAX; cfg.blocks.len()];
+        for (i, b) in order.iter().enumerate() {
+            rpo_index[*b] = i;
+        }
+        let mut idom = vec![None; cfg.blocks.len()];
+        idom[0] = Some(0);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &b in order.iter().skip(1) {
+                let mut new_idom = None;
+                for &p in &cfg.blocks[b].preds {
+                    if idom[p].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => p,
+                        Some(other) => intersect(&idom, &rpo_index, p, other),
+                    });
+                }
+                if idom[b] != new_idom {
+                    idom[b] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        let idom = idom
+            .into_iter()
+            .map(|d| d.expect("cfg with unreachable block"))
+            .collect();
+        Self { idom }
+    }
+    //None for the entry
+    pub fn idom(&self, b: usize) -> Option<usize> {
+        (b != 0).then(|| self.idom[b])
+    }
+    pub fn dominates(&self, a: usize, mut b: usize) -> bool {
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.idom(b) {
+                Some(d) => b = d,
+                None => return false,
+            }
+        }
+    }
+    //the dominator tree
+    pub fn children(&self) -> Vec<Vec<usize>> {
+        let mut children = vec![vec![]; self.idom.len()];
+        for b in 1..self.idom.len() {
+            children[self.idom[b]].push(b);
+        }
+        children
+    }
+    //blocks where the dominance of each block ends, that's where phis go
+    pub fn frontiers(&self, cfg: &Cfg) -> Vec<BTreeSet<usize>> {
+        let mut frontiers = vec![BTreeSet::new(); cfg.blocks.len()];
+        for (b, block) in cfg.blocks.iter().enumerate() {
+            if block.preds.len() < 2 {
+                continue;
+            }
+            for &p in &block.preds {
+                let mut runner = p;
+                while runner != self.idom[b] {
+                    frontiers[runner].insert(b);
+                    runner = self.idom[runner];
+                }
+            }
+        }
+        frontiers
+    }
+}
+fn intersect(idom: &[Option<usize>], rpo_index: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while rpo_index[a] > rpo_index[b] {
+            a = idom[a].unwrap();
+        }
+        while rpo_index[b] > rpo_index[a] {
+            b = idom[b].unwrap();
+        }
+    }
+    a
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use super::{BinOp, Function, Instr, Module, Operand, Place};
+
+//moves loop invariant computations in front of their loop and replaces multiplications
+//of induction variables by additions and multiplications by powers of two by shifts
+pub fn optimize_loops(module: &mut Module) {
+    for func in module.all_functions_mut() {
+        while find_loops(func)
+            .into_iter()
+            .any(|lp| hoist_invariants(func, lp) || reduce_induction(func, lp))
+        {}
+        shift_powers_of_two(func);
+    }
+}
+
+//a loop in the linear code: the header label at `start`, the jump back to it at `end`. only
+//loops that are entered by falling into the header count, what comes before is their preheader
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Loop {
+    start: usize,
+    end: usize,
+}
+impl Loop {
+    fn contains(&self, i: usize) -> bool {
+        (self.start..=self.end).contains(&i)
+    }
+}
+
+//innermost loops first
+fn find_loops(func: &Function) -> Vec<Loop> {
+    let labels = func
+        .body
+        .iter()
+        .enumerate()
+        .filter_map(|(i, instr)| match instr {
+            Instr::Label(label) => Some((label.as_str(), i)),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+    let mut ends = HashMap::new();
+    for (i, instr) in func.body.iter().enumerate() {
+        if let Instr::Jump(label) = instr {
+            if labels[label.as_str()] < i {
+                ends.insert(labels[label.as_str()], i);
+            }
+        }
+    }
+    let mut loops = ends
+        .into_iter()
+        .map(|(start, end)| Loop { start, end })
+        .filter(|lp| {
+            //nothing from outside jumps into the loop
+            func.body.iter().enumerate().all(|(i, instr)| {
+                lp.contains(i) || target(instr).is_none_or(|label| !lp.contains(labels[label]))
+            })
+        })
+        .collect::<Vec<_>>();
+    loops.sort_by_key(|lp| (lp.end - lp.start, lp.start));
+    loops
+}
+fn target(instr: &Instr) -> Option<&str> {
+    match instr {
+        Instr::Jump(label) | Instr::Branch { if_false: label, .. } => Some(label),
+        _ => None,
+    }
+}
+fn def_counts<'a>(instrs: impl Iterator<Item = &'a Instr>) -> HashMap<Place, usize> {
+    let mut counts = HashMap::new();
+    for place in instrs.filter_map(Instr::def) {
+        *counts.entry(place).or_insert(0) += 1;
+    }
+    counts
+}
+
+//temps with one definition whose operands don't change in the loop. add, sub, div and rem
+//can trap, in front of a loop that doesn't run they would trap where the program didn't
+fn hoist_invariants(func: &mut Function, lp: Loop) -> bool {
+    let in_func = def_counts(func.body.iter());
+    let mut in_loop = def_counts(func.body[lp.start..=lp.end].iter());
+    let mut hoisted = vec![];
+    for i in lp.start..=lp.end {
+        let Instr::BinOp { dst, op, lhs, rhs } = &func.body[i] else {
+            continue;
+        };
+        let invariant =
+            |operand: &Operand| operand.place().is_none_or(|place| !in_loop.contains_key(&place));
+        if matches!(dst, Place::Temp(_))
+            && in_func[dst] == 1
+            && matches!(op, BinOp::Mul | BinOp::Lt | BinOp::Shl)
+            && invariant(lhs)
+            && invariant(rhs)
+        {
+            in_loop.remove(dst);
+            hoisted.push(i);
+        }
+    }
+    for (moved, i) in hoisted.iter().enumerate() {
+        //everything before it that was moved is in front of the loop now
+        let instr = func.body.remove(*i);
+        func.body.insert(lp.start + moved, instr);
+    }
+    !hoisted.is_empty()
+}
+
+//`i` is a basic induction variable if the loop only sets it with `t = i + c; i = t`. then
+//`i * k` stays equal to a new variable that starts as `i * k` and grows by `c * k` with `i`.
+//the new variable uses a wrapping add like the multiplication it replaces
+fn reduce_induction(func: &mut Function, lp: Loop) -> bool {
+    let in_loop = def_counts(func.body[lp.start..=lp.end].iter());
+    let step_of = |var: Place| {
+        let update = (lp.start..=lp.end).find(|i| func.body[*i].def() == Some(var))?;
+        let Instr::Copy { src: Operand::Place(temp), .. } = func.body[update] else {
+            return None;
+        };
+        let step = func.body[lp.start..=lp.end].iter().find_map(|instr| match instr {
+            Instr::BinOp { dst, op, lhs, rhs } if *dst == temp => match (op, lhs, rhs) {
+                (BinOp::Add, Operand::Place(i), Operand::Const(c)) if *i == var => Some(*c),
+                (BinOp::Add, Operand::Const(c), Operand::Place(i)) if *i == var => Some(*c),
+                (BinOp::Sub, Operand::Place(i), Operand::Const(c)) if *i == var => c.checked_neg(),
+                _ => None,
+            },
+            _ => None,
+        })?;
+        (in_loop.get(&var) == Some(&1) && in_loop.get(&temp) == Some(&1)).then_some((update, step))
+    };
+    let found = (lp.start..=lp.end).find_map(|i| {
+        let Instr::BinOp { dst, op: BinOp::Mul, lhs, rhs } = &func.body[i] else {
+            return None;
+        };
+        let (var, factor) = match (lhs, rhs) {
+            (Operand::Place(var @ Place::Var(_)), Operand::Const(k)) => (*var, *k),
+            (Operand::Const(k), Operand::Place(var @ Place::Var(_))) => (*var, *k),
+            _ => return None,
+        };
+        let (update, step) = step_of(var)?;
+        Some((i, *dst, var, factor, update, step.checked_mul(factor)?))
+    });
+    let Some((mul, dst, var, factor, update, increment)) = found else {
+        return false;
+    };
+    let Place::Var(n) = var else { unreachable!() };
+    let scaled = func.new_var(format!("{}*{factor}", func.var_names[n]));
+    func.body[mul] = Instr::Copy {
+        dst,
+        src: scaled.into(),
+    };
+    func.body.insert(
+        update + 1,
+        Instr::BinOp {
+            dst: scaled,
+            op: BinOp::Addu,
+            lhs: scaled.into(),
+            rhs: Operand::Const(increment),
+        },
+    );
+    func.body.insert(
+        lp.start,
+        Instr::BinOp {
+            dst: scaled,
+            op: BinOp::Mul,
+            lhs: var.into(),
+            rhs: Operand::Const(factor),
+        },
+    );
+    true
+}
+
+//x * 2^k keeps the low 32 bits just like x << k
+fn shift_powers_of_two(func: &mut Function) {
+    for instr in &mut func.body {
+        if let Instr::BinOp { op: op @ BinOp::Mul, lhs, rhs, .. } = instr {
+            if let Operand::Const(k) = lhs {
+                if k.count_ones() == 1 && *k > 0 {
+                    std::mem::swap(lhs, rhs);
+                }
+            }
+            if let Operand::Const(k) = rhs {
+                if k.count_ones() == 1 && *k > 0 {
+                    *op = BinOp::Shl;
+                    *rhs = Operand::Const(k.trailing_zeros() as i32);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile, lower_source, mips::sim::run};
+
+    #[test]
+    fn hoists_invariant_products() {
+        let s = "def f(a, b) { i sei 0; while (i < 3) { print(a * b); i sei i + 1 } }";
+        let module = lower_source(s).unwrap();
+        let expected = "func f(a@0, b@1):
+    i@2 = 0
+    t1 = a@0 * b@1
+_L0:
+    t0 = i@2 < 3
+    ifnot t0 goto _L1
+    syscall 1(t1)
+    t2 = i@2 + 1
+    i@2 = t2
+    goto _L0
+_L1:
+    return 0
+";
+        assert_eq!(module.functions[0].to_string(), expected);
+    }
+    #[test]
+    fn reduces_induction_variables_and_powers_of_two() {
+        let s = "i sei 0; while (i < 4) { print(i * 3); print(i * 8); i sei i + 1 }";
+        let expected = "func main():
+    i@0 = 0
+    i*3@1 = i@0 * 3
+    i*8@2 = i@0 << 3
+_L0:
+    t0 = i@0 < 4
+    ifnot t0 goto _L1
+    t1 = i*3@1
+    syscall 1(t1)
+    t2 = i*8@2
+    syscall 1(t2)
+    t3 = i@0 + 1
+    i@0 = t3
+    i*8@2 = i*8@2 +u 8
+    i*3@1 = i*3@1 +u 3
+    goto _L0
+_L1:
+    return 0
+";
+        assert_eq!(lower_source(s).unwrap().main.to_string(), expected);
+        assert_eq!(run(&compile(s, false).unwrap(), ""), "0038616924");
+    }
+}
@@ -0,0 +1,382 @@
+use crate::{
+    expression::{
+        statements::{ArrayLit, FrameStack, Var},
+        CodeBlock, Expression, Node,
+    },
+    lexer::parse_int,
+    opt::const_fold::const_value,
+};
+
+use super::{BinOp, Callee, Function, Instr, Label, Module, Operand, Place};
+
+const OUT_OF_BOUNDS: &str = "index out of bounds\n";
+const BUFFER_TOO_SMALL: &str = "buffer too small\n";
+
+//turns the ast into three address code, every FuncDecl becomes its own function.
+//`bounds_checks` stops the program with a message when an index is outside its array,
+//`read_string` always stops it when the buffer is too small
+pub fn lower(program: &CodeBlock, bounds_checks: bool) -> Module {
+    let mut module = Module {
+        main: Function::default(),
+        functions: vec![],
+        strings: vec![],
+        floats: vec![],
+    };
+    let mut labels = 0;
+    let frame = program.frame.as_ref().expect("program without frame");
+    let mut lowerer = Lowerer {
+        func: Function::default(),
+        labels: &mut labels,
+        module: &mut module,
+        bounds_checks,
+        stops: vec![],
+    };
+    let main = lowerer.lower_function("main", &[], program, frame);
+    module.main = main;
+    module
+}
+
+struct Lowerer<'a> {
+    func: Function,
+    labels: &'a mut usize,
+    //gets the other functions and the strings
+    module: &'a mut Module,
+    bounds_checks: bool,
+    //where the failed checks of the function jump to, to print the message and stop
+    stops: Vec<(&'static str, Label)>,
+}
+impl Lowerer<'_> {
+    fn lower_function(
+        &mut self,
+        name: &str,
+        params: &[Var],
+        body: &dyn Expression,
+        frame: &FrameStack,
+    ) -> Function {
+        let func = Function {
+            name: name.to_string(),
+            params: params.iter().map(|p| p.addr).collect(),
+            var_names: vec![String::new(); frame.n_slots()],
+            ..Function::default()
+        };
+        let outer = std::mem::replace(&mut self.func, func);
+        let outer_stops = std::mem::take(&mut self.stops);
+        for param in params {
+            self.name_var(param.addr, &param.name);
+        }
+        //a function returns the value of its last line
+        let value = self.lower_expr(body).unwrap_or(Operand::Const(0));
+        self.push(Instr::Return(value));
+        for (message, label) in std::mem::replace(&mut self.stops, outer_stops) {
+            self.push(Instr::Label(label));
+            let message = self.string(message);
+            self.syscall(4, vec![message]);
+            self.syscall(10, vec![]);
+        }
+        std::mem::replace(&mut self.func, outer)
+    }
+    fn push(&mut self, instr: Instr) {
+        self.func.body.push(instr);
+    }
+    fn new_label(&mut self) -> Label {
+        *self.labels += 1;
+        format!("_L{}", *self.labels - 1)
+    }
+    fn name_var(&mut self, addr: usize, name: &str) {
+        if self.func.var_names.len() <= addr {
+            self.func.var_names.resize(addr + 1, String::new());
+        }
+        self.func.var_names[addr] = name.to_string();
+    }
+    fn value_of(&mut self, expr: &dyn Expression) -> Operand {
+        self.lower_expr(expr)
+            .unwrap_or_else(|| panic!("{} has no value", expr.get_name()))
+    }
+    //returns where the value of the expression ends up, None for statements
+    fn lower_expr(&mut self, expr: &dyn Expression) -> Option<Operand> {
+        match expr.node() {
+            Node::Block(block) => {
+                let mut value = None;
+                for line in &block.lines {
+                    value = self.lower_expr(line.as_ref());
+                }
+                value
+            }
+            Node::Number(number) if number.is_float() => {
+                let x = number
+                    .0
+                    .parse::<f32>()
+                    .unwrap_or_else(|_| panic!("unsupported number {}", number.0));
+                let floats = &mut self.module.floats;
+                let n = match floats.iter().position(|y| y.to_bits() == x.to_bits()) {
+                    Some(n) => n,
+                    None => {
+                        floats.push(x);
+                        floats.len() - 1
+                    }
+                };
+                Some(Operand::Float(n))
+            }
+            Node::Number(number) => {
+                let value = parse_int(&number.0).unwrap_or_else(|e| panic!("{e}"));
+                Some(Operand::Const(value))
+            }
+            Node::Str(string) => Some(self.string(&string.0)),
+            Node::Bool(value) => Some(Operand::Const(value.0 as i32)),
+            Node::Var(var) => {
+                self.name_var(var.addr, &var.name);
+                Some(Place::Var(var.addr).into())
+            }
+            Node::BinOp(op) => {
+                let lhs = self.value_of(op.values.0.as_ref());
+                let rhs = self.value_of(op.values.1.as_ref());
+                let dst = self.func.new_temp();
+                self.push(Instr::BinOp {
+                    dst,
+                    op: op.op,
+                    lhs,
+                    rhs,
+                });
+                Some(dst.into())
+            }
+            Node::VarDecl(decl) => {
+                let src = self.value_of(decl.value.as_ref());
+                self.name_var(decl.addr, &decl.name);
+                self.push(Instr::Copy {
+                    dst: Place::Var(decl.addr),
+                    src,
+                });
+                None
+            }
+            Node::If(if_block) => {
+                let end = self.new_label();
+                let cond = self.value_of(if_block.cond.as_ref());
+                self.push(Instr::Branch {
+                    cond,
+                    if_false: end.clone(),
+                });
+                self.lower_expr(if_block.code.as_ref());
+                self.push(Instr::Label(end));
+                None
+            }
+            Node::While(while_block) => {
+                let start = self.new_label();
+                let end = self.new_label();
+                self.push(Instr::Label(start.clone()));
+                let cond = self.value_of(while_block.cond.as_ref());
+                self.push(Instr::Branch {
+                    cond,
+                    if_false: end.clone(),
+                });
+                self.lower_expr(while_block.code.as_ref());
+                self.push(Instr::Jump(start));
+                self.push(Instr::Label(end));
+                None
+            }
+            Node::Call(call) => {
+                let args = call
+                    .args
+                    .iter()
+                    .map(|arg| self.value_of(arg.as_ref()))
+                    .collect::<Vec<_>>();
+                let callee = call.func.callee();
+                if let (Callee::Syscall { code: 8, .. }, [buf, len]) = (&callee, &args[..]) {
+                    self.check_buffer(*buf, *len);
+                }
+                let dst = callee.returns().then(|| self.func.new_temp());
+                self.push(Instr::Call { dst, callee, args });
+                dst.map(Operand::from)
+            }
+            Node::Return(ret) => {
+                let value = self.value_of(ret.value.as_ref());
+                self.push(Instr::Return(value));
+                None
+            }
+            Node::FuncDecl(decl) => {
+                let func =
+                    self.lower_function(&decl.name, &decl.args, decl.body.as_ref(), &decl.frame);
+                self.module.functions.push(func);
+                None
+            }
+            Node::Array(array) => Some(self.lower_array(array)),
+            Node::Index(index) => {
+                let (base, offset) = self.element(index.array.as_ref(), index.index.as_ref());
+                let dst = self.func.new_temp();
+                self.push(Instr::Load { dst, base, offset });
+                Some(dst.into())
+            }
+            Node::IndexAssign(assign) => {
+                let (base, offset) = self.element(assign.array.as_ref(), assign.index.as_ref());
+                let value = self.value_of(assign.value.as_ref());
+                self.push(Instr::Store { base, offset, value });
+                None
+            }
+            Node::StructDecl(_) | Node::Import(_) => None,
+            //a word per field, from sbrk like the vectors
+            Node::StructLit(lit) => {
+                let values = lit.values.iter().map(|v| self.value_of(v.as_ref()));
+                let values = values.collect::<Vec<_>>();
+                let base = self.func.new_temp();
+                self.push(Instr::Call {
+                    dst: Some(base),
+                    callee: Callee::Syscall { code: 9, returns: true },
+                    args: vec![Operand::Const(4 * values.len() as i32)],
+                });
+                for (i, value) in values.into_iter().enumerate() {
+                    self.push(Instr::Store { base: base.into(), offset: 4 * i as i32, value });
+                }
+                Some(base.into())
+            }
+            Node::Field(field) => {
+                let base = self.value_of(field.object.as_ref());
+                let dst = self.func.new_temp();
+                self.push(Instr::Load { dst, base, offset: 4 * field.slot as i32 });
+                Some(dst.into())
+            }
+            Node::FieldAssign(assign) => {
+                let base = self.value_of(assign.object.as_ref());
+                let value = self.value_of(assign.value.as_ref());
+                self.push(Instr::Store { base, offset: 4 * assign.slot as i32, value });
+                None
+            }
+        }
+    }
+    fn string(&mut self, string: &str) -> Operand {
+        let strings = &mut self.module.strings;
+        let n = match strings.iter().position(|s| s == string) {
+            Some(n) => n,
+            None => {
+                strings.push(string.to_string());
+                strings.len() - 1
+            }
+        };
+        Operand::Str(n)
+    }
+    fn syscall(&mut self, code: i32, args: Vec<Operand>) {
+        let callee = Callee::Syscall { code, returns: false };
+        self.push(Instr::Call { dst: None, callee, args });
+    }
+    //arrays are the length, the address of the elements and the elements, in the frame of the
+    //function. vectors start the same way
+    fn lower_array(&mut self, array: &ArrayLit) -> Operand {
+        let values = array.values.iter().map(|v| self.value_of(v.as_ref())).collect::<Vec<_>>();
+        let len = match &array.len {
+            Some(len) => const_value(len.as_ref()).expect("array length isn't constant") as usize,
+            None => values.len(),
+        };
+        let base = self.func.new_temp();
+        let slot = self.func.frame_words;
+        self.func.frame_words += len + 2;
+        self.push(Instr::FrameAddr { dst: base, slot });
+        let store = |offset, value| Instr::Store { base: base.into(), offset, value };
+        let add = |dst, lhs: Place, bytes| Instr::BinOp {
+            dst,
+            op: BinOp::Addu,
+            lhs: lhs.into(),
+            rhs: Operand::Const(bytes),
+        };
+        self.push(store(0, Operand::Const(len as i32)));
+        let elems = self.func.new_temp();
+        self.push(add(elems, base, 8));
+        self.push(store(4, elems.into()));
+        if array.len.is_none() {
+            for (i, value) in values.into_iter().enumerate() {
+                self.push(store(4 * (i as i32 + 2), value));
+            }
+            return base.into();
+        }
+        //`[value; len]` fills the elements in a loop
+        let (start, end) = (self.new_label(), self.new_label());
+        let ptr = self.func.new_var(String::from("_fill"));
+        let last = self.func.new_temp();
+        self.push(Instr::Copy { dst: ptr, src: elems.into() });
+        self.push(add(last, elems, 4 * len as i32));
+        self.push(Instr::Label(start.clone()));
+        let cond = self.func.new_temp();
+        self.push(Instr::BinOp { dst: cond, op: BinOp::Lt, lhs: ptr.into(), rhs: last.into() });
+        self.push(Instr::Branch { cond: cond.into(), if_false: end.clone() });
+        self.push(Instr::Store { base: ptr.into(), offset: 0, value: values[0] });
+        self.push(add(ptr, ptr, 4));
+        self.push(Instr::Jump(start));
+        self.push(Instr::Label(end));
+        base.into()
+    }
+    //base and byte offset of `array[index]`, with the bounds checked if they are turned on
+    fn element(&mut self, array: &dyn Expression, index: &dyn Expression) -> (Operand, i32) {
+        let array = self.value_of(array);
+        let index = self.value_of(index);
+        if self.bounds_checks {
+            self.check_bounds(array, index);
+        }
+        let elems = self.func.new_temp();
+        self.push(Instr::Load { dst: elems, base: array, offset: 4 });
+        //the offset has to fit into the instruction
+        if let Operand::Const(i) = index {
+            if let Some(offset) = i.checked_mul(4).filter(|&o| i16::try_from(o).is_ok()) {
+                return (elems.into(), offset);
+            }
+        }
+        let scaled = self.func.new_temp();
+        self.push(Instr::BinOp { dst: scaled, op: BinOp::Shl, lhs: index, rhs: Operand::Const(2) });
+        let addr = self.func.new_temp();
+        let (lhs, rhs) = (elems.into(), scaled.into());
+        self.push(Instr::BinOp { dst: addr, op: BinOp::Addu, lhs, rhs });
+        (addr.into(), 0)
+    }
+    //0 <= index < length, or off to the error at the end of the function
+    //the label that prints `message` and stops the program
+    fn stop(&mut self, message: &'static str) -> Label {
+        if let Some((_, label)) = self.stops.iter().find(|(m, _)| *m == message) {
+            return label.clone();
+        }
+        let label = self.new_label();
+        self.stops.push((message, label.clone()));
+        label
+    }
+    fn check_bounds(&mut self, array: Operand, index: Operand) {
+        let label = self.stop(OUT_OF_BOUNDS);
+        let len = self.func.new_temp();
+        self.push(Instr::Load { dst: len, base: array, offset: 0 });
+        for (lhs, rhs) in [(Operand::Const(-1), index), (index, len.into())] {
+            let cond = self.func.new_temp();
+            self.push(Instr::BinOp { dst: cond, op: BinOp::Lt, lhs, rhs });
+            self.push(Instr::Branch { cond: cond.into(), if_false: label.clone() });
+        }
+    }
+    //the size of a buffer is in the word before it, a line of len - 1 characters and the 0
+    //after them have to fit
+    fn check_buffer(&mut self, buf: Operand, len: Operand) {
+        let label = self.stop(BUFFER_TOO_SMALL);
+        let size = self.func.new_temp();
+        self.push(Instr::Load { dst: size, base: buf, offset: -4 });
+        let room = self.func.new_temp();
+        let (op, rhs) = (BinOp::Add, Operand::Const(1));
+        self.push(Instr::BinOp { dst: room, op, lhs: size.into(), rhs });
+        let cond = self.func.new_temp();
+        self.push(Instr::BinOp { dst: cond, op: BinOp::Lt, lhs: len, rhs: room.into() });
+        self.push(Instr::Branch { cond: cond.into(), if_false: label });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lower_source;
+
+    #[test]
+    fn lowers_control_flow() {
+        let ir = lower_source("i sei 0; while (i < 3) { print(i); i sei i + 1 }").unwrap();
+        let expected = "func main():
+    i@0 = 0
+_L0:
+    t0 = i@0 < 3
+    ifnot t0 goto _L1
+    syscall 1(i@0)
+    t1 = i@0 + 1
+    i@0 = t1
+    goto _L0
+_L1:
+    return 0
+";
+        assert_eq!(ir.to_string(), expected);
+    }
+}
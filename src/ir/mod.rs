@@ -0,0 +1,419 @@
+use std::{collections::HashMap, fmt};
+
+use crate::lexer::escape_str;
+
+use self::runtime::Runtime;
+
+pub mod cfg;
+pub mod codegen;
+pub mod dom;
+pub mod loops;
+pub mod lower;
+pub mod regalloc;
+pub mod runtime;
+pub mod ssa;
+pub mod tail_call;
+
+//where a value lives: a compiler generated temporary or a variable slot of the frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Place {
+    Temp(usize),
+    Var(usize),
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operand {
+    Place(Place),
+    Const(i32),
+    //address of a string of the module
+    Str(usize),
+    //bits of a float constant of the module
+    Float(usize),
+}
+impl From<Place> for Operand {
+    fn from(place: Place) -> Self {
+        Operand::Place(place)
+    }
+}
+impl Operand {
+    pub fn place(&self) -> Option<Place> {
+        match self {
+            Operand::Place(place) => Some(*place),
+            Operand::Const(_) | Operand::Str(_) | Operand::Float(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Lt,
+    //made by the optimizer and for addresses: shifts by a constant amount and an add that
+    //wraps around
+    Shl,
+    Addu,
+    //floats are kept as their bits like any other value, these work on them
+    AddF,
+    SubF,
+    MulF,
+    DivF,
+    LtF,
+}
+impl BinOp {
+    pub fn sign(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Lt => "<",
+            BinOp::Shl => "<<",
+            BinOp::Addu => "+u",
+            BinOp::AddF => "+.",
+            BinOp::SubF => "-.",
+            BinOp::MulF => "*.",
+            BinOp::DivF => "/.",
+            BinOp::LtF => "<.",
+        }
+    }
+    //the same operation on floats
+    pub fn on_floats(self) -> Option<BinOp> {
+        match self {
+            BinOp::Add => Some(BinOp::AddF),
+            BinOp::Sub => Some(BinOp::SubF),
+            BinOp::Mul => Some(BinOp::MulF),
+            BinOp::Div => Some(BinOp::DivF),
+            BinOp::Lt => Some(BinOp::LtF),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Callee {
+    Func(String),
+    //builtins map straight to a syscall, arguments go to $a0.., the result comes from $v0
+    Syscall { code: i32, returns: bool },
+    //int to float and back, `int` cuts off the fraction
+    ToFloat,
+    ToInt,
+    //a routine of the runtime, called like a function
+    Runtime(Runtime),
+    //length of an array or vector, the word its address points at
+    Len,
+}
+impl Callee {
+    //whether the call gives a value
+    pub fn returns(&self) -> bool {
+        !matches!(self, Callee::Syscall { returns: false, .. } | Callee::Runtime(Runtime::Push))
+    }
+}
+
+pub type Label = String;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instr {
+    Copy {
+        dst: Place,
+        src: Operand,
+    },
+    BinOp {
+        dst: Place,
+        op: BinOp,
+        lhs: Operand,
+        rhs: Operand,
+    },
+    Label(Label),
+    Jump(Label),
+    //jumps if cond is zero
+    Branch {
+        cond: Operand,
+        if_false: Label,
+    },
+    Call {
+        dst: Option<Place>,
+        callee: Callee,
+        args: Vec<Operand>,
+    },
+    Return(Operand),
+    //address of word `slot` of the arrays in the frame
+    FrameAddr {
+        dst: Place,
+        slot: usize,
+    },
+    //the word at base + offset, offset is in bytes
+    Load {
+        dst: Place,
+        base: Operand,
+        offset: i32,
+    },
+    Store {
+        base: Operand,
+        offset: i32,
+        value: Operand,
+    },
+    //only in ssa form, picks the operand of the block control came from
+    Phi {
+        dst: Place,
+        srcs: Vec<(Label, Operand)>,
+    },
+}
+impl Instr {
+    //the place written by this instruction
+    pub fn def(&self) -> Option<Place> {
+        match self {
+            Instr::Copy { dst, .. }
+            | Instr::BinOp { dst, .. }
+            | Instr::Phi { dst, .. }
+            | Instr::FrameAddr { dst, .. }
+            | Instr::Load { dst, .. } => Some(*dst),
+            Instr::Call { dst, .. } => *dst,
+            _ => None,
+        }
+    }
+    //all operands read by this instruction
+    pub fn uses(&self) -> Vec<Operand> {
+        match self {
+            Instr::Copy { src, .. } => vec![*src],
+            Instr::BinOp { lhs, rhs, .. } => vec![*lhs, *rhs],
+            Instr::Branch { cond, .. } => vec![*cond],
+            Instr::Call { args, .. } => args.clone(),
+            Instr::Return(value) => vec![*value],
+            Instr::Phi { srcs, .. } => srcs.iter().map(|(_, src)| *src).collect(),
+            Instr::Load { base, .. } => vec![*base],
+            Instr::Store { base, value, .. } => vec![*base, *value],
+            Instr::Label(_) | Instr::Jump(_) | Instr::FrameAddr { .. } => vec![],
+        }
+    }
+    pub fn map_def(&mut self, f: impl FnOnce(Place) -> Place) {
+        match self {
+            Instr::Copy { dst, .. }
+            | Instr::BinOp { dst, .. }
+            | Instr::Phi { dst, .. }
+            | Instr::FrameAddr { dst, .. }
+            | Instr::Load { dst, .. } => *dst = f(*dst),
+            Instr::Call { dst: Some(dst), .. } => *dst = f(*dst),
+            _ => (),
+        }
+    }
+    pub fn map_uses(&mut self, mut f: impl FnMut(Place) -> Place) {
+        let mut map = |operand: &mut Operand| {
+            if let Operand::Place(place) = operand {
+                *place = f(*place);
+            }
+        };
+        match self {
+            Instr::Copy { src, .. } => map(src),
+            Instr::BinOp { lhs, rhs, .. } => {
+                map(lhs);
+                map(rhs);
+            }
+            Instr::Branch { cond, .. } => map(cond),
+            Instr::Call { args, .. } => args.iter_mut().for_each(map),
+            Instr::Return(value) => map(value),
+            Instr::Phi { srcs, .. } => srcs.iter_mut().for_each(|(_, src)| map(src)),
+            Instr::Load { base, .. } => map(base),
+            Instr::Store { base, value, .. } => {
+                map(base);
+                map(value);
+            }
+            Instr::Label(_) | Instr::Jump(_) | Instr::FrameAddr { .. } => (),
+        }
+    }
+    //jumps, branches and returns end a basic block
+    pub fn is_terminator(&self) -> bool {
+        matches!(self, Instr::Jump(_) | Instr::Branch { .. } | Instr::Return(_))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    //slots the arguments get copied into
+    pub params: Vec<usize>,
+    //names of the variable slots, only for printing
+    pub var_names: Vec<String>,
+    pub n_temps: usize,
+    //words the arrays take in the frame
+    pub frame_words: usize,
+    pub body: Vec<Instr>,
+}
+impl Function {
+    pub fn n_vars(&self) -> usize {
+        self.var_names.len()
+    }
+    pub fn new_temp(&mut self) -> Place {
+        self.n_temps += 1;
+        Place::Temp(self.n_temps - 1)
+    }
+    pub fn new_var(&mut self, name: String) -> Place {
+        self.var_names.push(name);
+        Place::Var(self.var_names.len() - 1)
+    }
+    //drops slots nothing refers to anymore, params keep their place in front
+    pub fn compact(&mut self) {
+        let mut vars = HashMap::new();
+        let mut temps = HashMap::new();
+        let mut renumber = |place: Place| match place {
+            Place::Var(n) => {
+                let next = vars.len();
+                Place::Var(*vars.entry(n).or_insert(next))
+            }
+            Place::Temp(n) => {
+                let next = temps.len();
+                Place::Temp(*temps.entry(n).or_insert(next))
+            }
+        };
+        for param in &mut self.params {
+            if let Place::Var(n) = renumber(Place::Var(*param)) {
+                *param = n;
+            }
+        }
+        for instr in &mut self.body {
+            instr.map_def(&mut renumber);
+            instr.map_uses(&mut renumber);
+        }
+        let mut var_names = vec![String::new(); vars.len()];
+        for (old, new) in vars {
+            var_names[new] = std::mem::take(&mut self.var_names[old]);
+        }
+        self.var_names = var_names;
+        self.n_temps = temps.len();
+    }
+}
+
+//the whole program, `main` is the top level code
+#[derive(Clone, Debug, PartialEq)]
+pub struct Module {
+    pub main: Function,
+    pub functions: Vec<Function>,
+    //string literals, Operand::Str indexes into them
+    pub strings: Vec<String>,
+    //float literals for Operand::Float
+    pub floats: Vec<f32>,
+}
+impl Module {
+    pub fn all_functions(&self) -> impl Iterator<Item = &Function> {
+        std::iter::once(&self.main).chain(&self.functions)
+    }
+    pub fn all_functions_mut(&mut self) -> impl Iterator<Item = &mut Function> {
+        std::iter::once(&mut self.main).chain(&mut self.functions)
+    }
+}
+
+//printing, e.g. `t1 = x@0 + 2`
+struct PlaceIn<'a>(&'a Function, Place);
+impl fmt::Display for PlaceIn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Place::Temp(n) => write!(f, "t{n}"),
+            Place::Var(n) => match self.0.var_names.get(n) {
+                Some(name) => write!(f, "{name}@{n}"),
+                None => write!(f, "@{n}"),
+            },
+        }
+    }
+}
+struct OperandIn<'a>(&'a Function, Operand);
+impl fmt::Display for OperandIn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Operand::Place(place) => write!(f, "{}", PlaceIn(self.0, place)),
+            Operand::Const(value) => write!(f, "{value}"),
+            Operand::Str(n) => write!(f, "str{n}"),
+            Operand::Float(n) => write!(f, "flt{n}"),
+        }
+    }
+}
+impl Function {
+    pub fn fmt_instr(&self, instr: &Instr) -> String {
+        let place = |p: &Place| PlaceIn(self, *p).to_string();
+        let operand = |o: &Operand| OperandIn(self, *o).to_string();
+        match instr {
+            Instr::Copy { dst, src } => format!("{} = {}", place(dst), operand(src)),
+            Instr::BinOp { dst, op, lhs, rhs } => format!(
+                "{} = {} {} {}",
+                place(dst),
+                operand(lhs),
+                op.sign(),
+                operand(rhs)
+            ),
+            Instr::Label(label) => format!("{label}:"),
+            Instr::Jump(label) => format!("goto {label}"),
+            Instr::Branch { cond, if_false } => format!("ifnot {} goto {if_false}", operand(cond)),
+            Instr::Call { dst, callee, args } => {
+                let args = args.iter().map(operand).collect::<Vec<_>>().join(", ");
+                let call = match callee {
+                    Callee::Func(name) => format!("call {name}({args})"),
+                    Callee::Syscall { code, .. } => format!("syscall {code}({args})"),
+                    Callee::ToFloat => format!("float({args})"),
+                    Callee::ToInt => format!("int({args})"),
+                    Callee::Runtime(runtime) => format!("call {}({args})", runtime.label()),
+                    Callee::Len => format!("len({args})"),
+                };
+                match dst {
+                    Some(dst) => format!("{} = {call}", place(dst)),
+                    None => call,
+                }
+            }
+            Instr::Return(value) => format!("return {}", operand(value)),
+            Instr::FrameAddr { dst, slot } => format!("{} = &frame[{slot}]", place(dst)),
+            Instr::Load { dst, base, offset } => {
+                format!("{} = *({} + {offset})", place(dst), operand(base))
+            }
+            Instr::Store { base, offset, value } => {
+                format!("*({} + {offset}) = {}", operand(base), operand(value))
+            }
+            Instr::Phi { dst, srcs } => {
+                let srcs = srcs
+                    .iter()
+                    .map(|(label, src)| format!("{label}: {}", operand(src)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{} = phi({srcs})", place(dst))
+            }
+        }
+    }
+}
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = self
+            .params
+            .iter()
+            .map(|p| PlaceIn(self, Place::Var(*p)).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(f, "func {}({}):", self.name, params)?;
+        for instr in &self.body {
+            match instr {
+                Instr::Label(_) => writeln!(f, "{}", self.fmt_instr(instr))?,
+                _ => writeln!(f, "    {}", self.fmt_instr(instr))?,
+            }
+        }
+        Ok(())
+    }
+}
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (n, string) in self.strings.iter().enumerate() {
+            writeln!(f, "str{n} = {}", escape_str(string))?;
+        }
+        for (n, x) in self.floats.iter().enumerate() {
+            writeln!(f, "flt{n} = {x:?}")?;
+        }
+        if !self.strings.is_empty() || !self.floats.is_empty() {
+            writeln!(f)?;
+        }
+        for (i, func) in self.all_functions().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{func}")?;
+        }
+        Ok(())
+    }
+}
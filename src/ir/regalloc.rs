@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::mips::instr::Reg;
+
+use super::{Callee, Function, Instr, Place};
+
+//$t0 and $t1 stay free for loading spilled values and constants
+pub const TEMP_REGS: [Reg; 8] = [Reg::T2, Reg::T3, Reg::T4, Reg::T5, Reg::T6, Reg::T7, Reg::T8, Reg::T9];
+//callee saved, so values in them survive calls
+pub const SAVED_REGS: [Reg; 8] = [Reg::S0, Reg::S1, Reg::S2, Reg::S3, Reg::S4, Reg::S5, Reg::S6, Reg::S7];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Loc {
+    Reg(Reg),
+    //spill slot in the frame
+    Stack(usize),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Allocation {
+    pub locs: HashMap<Place, Loc>,
+    pub n_spills: usize,
+    //$s registers the function writes, it has to restore them before returning
+    pub saved_regs: Vec<Reg>,
+    //$t registers live across the call at that instruction, the caller saves them
+    pub call_saves: HashMap<usize, Vec<Reg>>,
+}
+impl Allocation {
+    pub fn loc(&self, place: Place) -> Loc {
+        self.locs[&place]
+    }
+}
+
+//first and last instruction a place is live at, holes are ignored
+#[derive(Clone, Copy, Debug)]
+struct Interval {
+    place: Place,
+    start: usize,
+    end: usize,
+}
+
+//linear scan after Poletto and Sarkar, over the instructions in body order
+pub fn allocate(func: &Function) -> Allocation {
+    let intervals = live_intervals(func);
+    let calls = func
+        .body
+        .iter()
+        .enumerate()
+        .filter(|(_, instr)| {
+            matches!(instr, Instr::Call { callee: Callee::Func(_) | Callee::Runtime(_), .. })
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let crosses_call = |interval: &Interval| {
+        calls
+            .iter()
+            .any(|&call| interval.start < call && call < interval.end)
+    };
+
+    let mut alloc = Allocation::default();
+    let mut active: Vec<(Interval, Reg)> = vec![];
+    let mut free = TEMP_REGS.iter().chain(&SAVED_REGS).copied().collect::<HashSet<_>>();
+    for &interval in &intervals {
+        active.retain(|(other, reg)| {
+            let live = other.end >= interval.start;
+            if !live {
+                free.insert(*reg);
+            }
+            live
+        });
+        //values living across a call are cheaper in $s registers, the others in $t registers
+        let (first, second) = if crosses_call(&interval) {
+            (&SAVED_REGS, &TEMP_REGS)
+        } else {
+            (&TEMP_REGS, &SAVED_REGS)
+        };
+        let reg = first
+            .iter()
+            .chain(second)
+            .find(|reg| free.contains(reg))
+            .copied();
+        if let Some(reg) = reg {
+            free.remove(&reg);
+            alloc.locs.insert(interval.place, Loc::Reg(reg));
+            active.push((interval, reg));
+            continue;
+        }
+        //no register left: the one living longest goes to the stack
+        let (longest, _) = active
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (other, _))| other.end)
+            .expect("no registers at all");
+        if active[longest].0.end > interval.end {
+            let (spilled, reg) = active.swap_remove(longest);
+            alloc.locs.insert(spilled.place, Loc::Stack(alloc.n_spills));
+            alloc.locs.insert(interval.place, Loc::Reg(reg));
+            active.push((interval, reg));
+        } else {
+            alloc.locs.insert(interval.place, Loc::Stack(alloc.n_spills));
+        }
+        alloc.n_spills += 1;
+    }
+
+    let mut saved = alloc
+        .locs
+        .values()
+        .filter_map(|loc| match loc {
+            Loc::Reg(reg) if SAVED_REGS.contains(reg) => Some(*reg),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    saved.sort();
+    saved.dedup();
+    alloc.saved_regs = saved;
+    for &call in &calls {
+        let mut saves = intervals
+            .iter()
+            .filter(|interval| interval.start < call && call < interval.end)
+            .filter_map(|interval| match alloc.loc(interval.place) {
+                Loc::Reg(reg) if TEMP_REGS.contains(&reg) => Some(reg),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        saves.sort();
+        alloc.call_saves.insert(call, saves);
+    }
+    alloc
+}
+
+//intervals sorted by start, params are live from the first instruction on
+fn live_intervals(func: &Function) -> Vec<Interval> {
+    let live_in = liveness(func);
+    let mut ranges: HashMap<Place, (usize, usize)> = HashMap::new();
+    let mut extend = |place: Place, i: usize| {
+        let range = ranges.entry(place).or_insert((i, i));
+        range.0 = range.0.min(i);
+        range.1 = range.1.max(i);
+    };
+    for param in &func.params {
+        extend(Place::Var(*param), 0);
+    }
+    for (i, instr) in func.body.iter().enumerate() {
+        for place in &live_in[i] {
+            extend(*place, i);
+        }
+        instr.def().into_iter().for_each(|place| extend(place, i));
+    }
+    let mut intervals = ranges
+        .into_iter()
+        .map(|(place, (start, end))| Interval { place, start, end })
+        .collect::<Vec<_>>();
+    intervals.sort_by_key(|interval| (interval.start, interval.end, interval.place));
+    intervals
+}
+
+//places live before each instruction
+fn liveness(func: &Function) -> Vec<HashSet<Place>> {
+    let labels = func
+        .body
+        .iter()
+        .enumerate()
+        .filter_map(|(i, instr)| match instr {
+            Instr::Label(label) => Some((label.as_str(), i)),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+    let n = func.body.len();
+    let succs = |i: usize| -> Vec<usize> {
+        match &func.body[i] {
+            Instr::Jump(label) => vec![labels[label.as_str()]],
+            Instr::Branch { if_false, .. } => vec![i + 1, labels[if_false.as_str()]],
+            Instr::Return(_) => vec![],
+            _ => vec![i + 1],
+        }
+        .into_iter()
+        .filter(|s| *s < n)
+        .collect()
+    };
+    let mut live_in = vec![HashSet::new(); n];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in (0..n).rev() {
+            let mut live = succs(i)
+                .into_iter()
+                .flat_map(|s| live_in[s].iter().copied())
+                .collect::<HashSet<_>>();
+            if let Some(def) = func.body[i].def() {
+                live.remove(&def);
+            }
+            live.extend(func.body[i].uses().iter().filter_map(|operand| operand.place()));
+            if live != live_in[i] {
+                live_in[i] = live;
+                changed = true;
+            }
+        }
+    }
+    live_in
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lower_source;
+
+    #[test]
+    fn keeps_values_across_calls_in_saved_registers() {
+        let module = lower_source(
+            "def g(x) { if (x < 1) { return 0 } g(x - 1) } def f(n) { a sei n * 2; b sei g(n); a + b }",
+        ).unwrap();
+        let f = &module.functions[1];
+        let alloc = allocate(f);
+        let a = f.var_names.iter().position(|name| name == "a").unwrap();
+        assert_eq!(alloc.loc(Place::Var(a)), Loc::Reg(Reg::S0));
+        assert_eq!(alloc.saved_regs, vec![Reg::S0]);
+        assert_eq!(alloc.n_spills, 0);
+    }
+}
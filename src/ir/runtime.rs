@@ -0,0 +1,101 @@
+use crate::mips::{
+    instr::{Instr as Mips, Item, Reg},
+    Code,
+};
+
+//routines the compiler adds to programs that use them. they are called like functions, with
+//the arguments on the stack, but don't set up a frame and only use the $t registers, which the
+//caller saves anyway.
+//vectors are 3 words on the heap: the length, the address of the elements and how many
+//elements fit there. the length and the address are where they are in an array, so indexing
+//works the same for both
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Runtime {
+    //`alloc(n)`: a vector of n zeros
+    Alloc,
+    //`push(v, x)`: appends x, moving the elements to twice the space when they're full
+    Push,
+    //`buffer(n)`: n zero bytes for `read_string`, their number is in the word before them
+    Buffer,
+}
+impl Runtime {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Runtime::Alloc => "_alloc",
+            Runtime::Push => "_push",
+            Runtime::Buffer => "_buffer",
+        }
+    }
+    //memory comes from sbrk and is never given back, the memory of the simulators starts out
+    //zeroed so alloc doesn't have to clear it
+    pub fn code(&self) -> Code {
+        use Reg::*;
+        let label = |suffix: &str| format!("{}_{suffix}", self.label());
+        let mut code = vec![Item::Label(self.label().to_string())];
+        let instrs = match self {
+            Runtime::Alloc => vec![
+                Mips::Lw(T1, 0, Sp).into(),
+                Mips::Sll(A0, T1, 2).into(),
+                Mips::Addi(A0, A0, 12).into(),
+                Mips::Addi(V0, Zero, 9).into(),
+                Mips::Syscall.into(),
+                Mips::Sw(T1, 0, V0).into(),
+                Mips::Addi(T0, V0, 12).into(),
+                Mips::Sw(T0, 4, V0).into(),
+                Mips::Sw(T1, 8, V0).into(),
+                Mips::Jr(Ra).into(),
+            ],
+            Runtime::Push => vec![
+                //$t0 the vector, $t1 its length
+                Mips::Lw(T0, 0, Sp).into(),
+                Mips::Lw(T1, 0, T0).into(),
+                Mips::Lw(T2, 8, T0).into(),
+                Mips::Slt(T3, T1, T2).into(),
+                Mips::Beqz(T3, label("grow")).into(),
+                Item::Label(label("store")),
+                Mips::Lw(T2, 4, T0).into(),
+                Mips::Sll(T3, T1, 2).into(),
+                Mips::Addu(T3, T2, T3).into(),
+                Mips::Lw(T4, 4, Sp).into(),
+                Mips::Sw(T4, 0, T3).into(),
+                Mips::Addi(T1, T1, 1).into(),
+                Mips::Sw(T1, 0, T0).into(),
+                Mips::Jr(Ra).into(),
+                //room for 2 * capacity + 4 elements, the old ones get copied over
+                Item::Label(label("grow")),
+                Mips::Sll(T2, T2, 1).into(),
+                Mips::Addi(T2, T2, 4).into(),
+                Mips::Sw(T2, 8, T0).into(),
+                Mips::Sll(A0, T2, 2).into(),
+                Mips::Addi(V0, Zero, 9).into(),
+                Mips::Syscall.into(),
+                Mips::Lw(T2, 4, T0).into(),
+                Mips::Add(T4, Zero, Zero).into(),
+                Mips::Sll(T5, T1, 2).into(),
+                Item::Label(label("copy")),
+                Mips::Slt(T3, T4, T5).into(),
+                Mips::Beqz(T3, label("copied")).into(),
+                Mips::Addu(T6, T2, T4).into(),
+                Mips::Lw(T6, 0, T6).into(),
+                Mips::Addu(T7, V0, T4).into(),
+                Mips::Sw(T6, 0, T7).into(),
+                Mips::Addi(T4, T4, 4).into(),
+                Mips::J(label("copy")).into(),
+                Item::Label(label("copied")),
+                Mips::Sw(V0, 4, T0).into(),
+                Mips::J(label("store")).into(),
+            ],
+            Runtime::Buffer => vec![
+                Mips::Lw(T1, 0, Sp).into(),
+                Mips::Addi(A0, T1, 4).into(),
+                Mips::Addi(V0, Zero, 9).into(),
+                Mips::Syscall.into(),
+                Mips::Sw(T1, 0, V0).into(),
+                Mips::Addi(V0, V0, 4).into(),
+                Mips::Jr(Ra).into(),
+            ],
+        };
+        code.extend(instrs);
+        code
+    }
+}
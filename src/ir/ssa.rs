@@ -0,0 +1,209 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use super::{cfg::Cfg, dom::Dominators, Function, Instr, Operand, Place};
+
+//every place gets exactly one definition, phis merge the versions where control flow joins.
+//only places read in another block than the one writing them get phis (semi-pruned ssa),
+//the original place stands for the value it has on entry
+pub fn construct(func: &mut Function, cfg: &mut Cfg) {
+    let doms = Dominators::compute(cfg);
+    let frontiers = doms.frontiers(cfg);
+    let mut globals = BTreeSet::new();
+    let mut def_blocks: HashMap<Place, Vec<usize>> = HashMap::new();
+    for (b, block) in cfg.blocks.iter().enumerate() {
+        let mut written = HashSet::new();
+        for instr in &block.instrs {
+            for used in instr.uses().iter().filter_map(Operand::place) {
+                if !written.contains(&used) {
+                    globals.insert(used);
+                }
+            }
+            if let Some(def) = instr.def() {
+                written.insert(def);
+                def_blocks.entry(def).or_default().push(b);
+            }
+        }
+    }
+    for place in globals {
+        let mut work = def_blocks.get(&place).cloned().unwrap_or_default();
+        let mut has_phi = HashSet::new();
+        while let Some(b) = work.pop() {
+            for &f in &frontiers[b] {
+                if !has_phi.insert(f) {
+                    continue;
+                }
+                let srcs = cfg.blocks[f]
+                    .preds
+                    .iter()
+                    .map(|p| (cfg.blocks[*p].label.clone(), place.into()))
+                    .collect();
+                cfg.blocks[f].instrs.insert(0, Instr::Phi { dst: place, srcs });
+                work.push(f);
+            }
+        }
+    }
+    let mut renamer = Renamer {
+        func,
+        versions: HashMap::new(),
+        counts: HashMap::new(),
+    };
+    renamer.rename(cfg, &doms.children(), 0);
+}
+
+struct Renamer<'a> {
+    func: &'a mut Function,
+    //stack of versions per original place, the top one is live
+    versions: HashMap<Place, Vec<Place>>,
+    counts: HashMap<Place, usize>,
+}
+impl Renamer<'_> {
+    fn current(&self, place: Place) -> Place {
+        self.versions
+            .get(&place)
+            .and_then(|stack| stack.last())
+            .copied()
+            .unwrap_or(place)
+    }
+    //variables keep their name with a version number, temporaries just get a new one
+    fn new_version(&mut self, place: Place) -> Place {
+        let version = match place {
+            Place::Temp(_) => self.func.new_temp(),
+            Place::Var(n) => {
+                let count = self.counts.entry(place).or_insert(0);
+                *count += 1;
+                let name = format!("{}.{count}", self.func.var_names[n]);
+                self.func.new_var(name)
+            }
+        };
+        self.versions.entry(place).or_default().push(version);
+        version
+    }
+    fn rename(&mut self, cfg: &mut Cfg, children: &[Vec<usize>], b: usize) {
+        let mut defined = vec![];
+        let mut instrs = std::mem::take(&mut cfg.blocks[b].instrs);
+        for instr in &mut instrs {
+            if !matches!(instr, Instr::Phi { .. }) {
+                instr.map_uses(|place| self.current(place));
+            }
+            instr.map_def(|place| {
+                defined.push(place);
+                self.new_version(place)
+            });
+        }
+        cfg.blocks[b].instrs = instrs;
+        let label = cfg.blocks[b].label.clone();
+        for s in cfg.blocks[b].succs.clone() {
+            for instr in &mut cfg.blocks[s].instrs {
+                let Instr::Phi { srcs, .. } = instr else {
+                    continue;
+                };
+                for (_, src) in srcs.iter_mut().filter(|(from, _)| *from == label) {
+                    if let Operand::Place(place) = src {
+                        *place = self.current(*place);
+                    }
+                }
+            }
+        }
+        for &child in &children[b] {
+            self.rename(cfg, children, child);
+        }
+        for place in defined {
+            self.versions.get_mut(&place).unwrap().pop();
+        }
+    }
+}
+
+//replaces the phis by copies at the end of the predecessors, critical edges get their own block
+pub fn destruct(func: &mut Function, cfg: &mut Cfg) {
+    for b in 0..cfg.blocks.len() {
+        let phis = cfg.blocks[b]
+            .instrs
+            .iter()
+            .filter_map(|instr| match instr {
+                Instr::Phi { dst, srcs } => Some((*dst, srcs.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if phis.is_empty() {
+            continue;
+        }
+        cfg.blocks[b].instrs.retain(|instr| !matches!(instr, Instr::Phi { .. }));
+        for p in cfg.blocks[b].preds.clone() {
+            let label = &cfg.blocks[p].label;
+            //all phis read before any is written, so the copies go through fresh temporaries
+            let mut reads = vec![];
+            let mut writes = vec![];
+            for (dst, srcs) in &phis {
+                let (_, src) = srcs
+                    .iter()
+                    .find(|(from, _)| from == label)
+                    .expect("phi without operand for predecessor");
+                let temp = func.new_temp();
+                reads.push(Instr::Copy { dst: temp, src: *src });
+                writes.push(Instr::Copy {
+                    dst: *dst,
+                    src: temp.into(),
+                });
+            }
+            reads.extend(writes);
+            let at = if cfg.blocks[p].succs.len() > 1 {
+                cfg.split_edge(p, b)
+            } else {
+                p
+            };
+            cfg.blocks[at].insert_before_terminator(reads);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compile, ir::codegen::gen_program, lower_source, mips::sim::run};
+
+    fn ssa_of(s: &str) -> String {
+        let mut func = lower_source(s).unwrap().main;
+        let mut cfg = Cfg::build(&func);
+        construct(&mut func, &mut cfg);
+        func.body = cfg.into_body();
+        func.compact();
+        func.to_string()
+    }
+
+    #[test]
+    fn places_phis_at_joins() {
+        let ssa = ssa_of("i sei 0; while (i < 3) { i sei i + 1 } print(i)");
+        let expected = "func main():
+_main_b0:
+    i.1@0 = 0
+_L0:
+    i.2@1 = phi(_main_b0: i.1@0, _main_b2: i.3@2)
+    t0 = i.2@1 < 3
+    ifnot t0 goto _L1
+_main_b2:
+    t1 = i.2@1 + 1
+    i.3@2 = t1
+    goto _L0
+_L1:
+    syscall 1(i.2@1)
+    return 0
+";
+        assert_eq!(ssa, expected);
+    }
+    #[test]
+    fn round_trip_keeps_behaviour() {
+        let s = "def f(n) { a sei 0; b sei 1; while (0 < n) { t sei a; a sei b; b sei t + b; n sei n - 1 } a }
+            def g(n, acc) { if (n < 1) { return acc } return g(n - 1, acc + n) }
+            i sei 0; while (i < 8) { if (i < 4) { print(f(i)) } i sei i + 1 } print(i); print(g(4, 0))";
+        let mut module = lower_source(s).unwrap();
+        for func in module.all_functions_mut() {
+            let mut cfg = Cfg::build(func);
+            construct(func, &mut cfg);
+            destruct(func, &mut cfg);
+            func.body = cfg.into_body();
+            func.compact();
+        }
+        assert_eq!(run(&gen_program(&module), ""), run(&compile(s, false).unwrap(), ""));
+        assert_eq!(run(&gen_program(&module), ""), "0112810");
+    }
+}
@@ -0,0 +1,82 @@
+use super::{Callee, Function, Instr, Module, Operand, Place};
+
+pub fn eliminate_tail_calls(module: &mut Module) {
+    for func in module.all_functions_mut() {
+        eliminate_in(func);
+    }
+}
+
+//`t = call f(..); return t` inside f becomes copies into the parameters and a jump back
+//to the start, so the recursion runs in the frame it started in
+fn eliminate_in(func: &mut Function) {
+    //the next round would fill its arrays over the ones the arguments might point to
+    if func.frame_words > 0 {
+        return;
+    }
+    let start = format!("_{}_start", func.name);
+    let body = std::mem::take(&mut func.body);
+    let mut found = false;
+    let mut instrs = body.into_iter().peekable();
+    while let Some(instr) = instrs.next() {
+        let args = match (&instr, instrs.peek()) {
+            (
+                Instr::Call { dst: Some(dst), callee: Callee::Func(name), args },
+                Some(Instr::Return(Operand::Place(value))),
+            ) if *name == func.name && value == dst && args.len() == func.params.len() => args.clone(),
+            _ => {
+                func.body.push(instr);
+                continue;
+            }
+        };
+        instrs.next();
+        found = true;
+        //a variable passed along might be a parameter set before it, those go through a temp
+        let params = func.params.clone();
+        let mut copies = vec![];
+        for (param, mut arg) in params.into_iter().zip(args) {
+            if arg == Operand::Place(Place::Var(param)) {
+                continue;
+            }
+            if let Operand::Place(Place::Var(_)) = arg {
+                let temp = func.new_temp();
+                func.body.push(Instr::Copy { dst: temp, src: arg });
+                arg = temp.into();
+            }
+            copies.push(Instr::Copy {
+                dst: Place::Var(param),
+                src: arg,
+            });
+        }
+        func.body.extend(copies);
+        func.body.push(Instr::Jump(start.clone()));
+    }
+    if found {
+        func.body.insert(0, Instr::Label(start));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lower_source;
+
+    #[test]
+    fn jumps_instead_of_calling() {
+        let module = lower_source(
+            "def sum(n, acc) { if (n < 1) { return acc } return sum(n - 1, acc + n) } print(sum(4, 0))",
+        ).unwrap();
+        let expected = "func sum(n@0, acc@1):
+_sum_start:
+    t0 = n@0 < 1
+    ifnot t0 goto _L0
+    return acc@1
+_L0:
+    t1 = n@0 - 1
+    t2 = acc@1 + n@0
+    n@0 = t1
+    acc@1 = t2
+    goto _sum_start
+    return 0
+";
+        assert_eq!(module.functions[0].to_string(), expected);
+    }
+}
@@ -41,8 +41,18 @@ impl CharStream {
         self.skip_next = true;
         n_bytes
     }
+    //the name after a dot, 0 if there is none
+    fn count_member_bytes(&mut self) -> usize {
+        let mut first = true;
+        self.count_bytes_while(|c| {
+            let later = !first && (c.is_ascii_digit() || c == '_');
+            let part_of_name = c.is_ascii_alphabetic() || later;
+            first = false;
+            part_of_name
+        })
+    }
 }
-pub fn lex(s: &str) -> Vec<Token> {
+pub fn lex(s: &str) -> Vec<Token<'_>> {
     let mut tokens = Vec::with_capacity(s.len() / 2 + 2);
     let mut stream = CharStream::new(s);
     let mut i_bytes = 0;
@@ -52,23 +62,30 @@ pub fn lex(s: &str) -> Vec<Token> {
         match c {
             'a'..='z' | 'A'..='Z' => {
                 token_length +=
-                    stream.count_bytes_while(|c| matches!(c,'0'..='9'|'a'..='z'|'A'..='Z'));
+                    stream.count_bytes_while(|c| matches!(c,'0'..='9'|'a'..='z'|'A'..='Z'|'_'));
                 token_type = TokenType::Word;
             }
             '0'..='9' => {
                 //maybe refactor to seperate function?
-                token_length += stream.count_bytes_while(|c| matches!(c, '0'..='9'));
+                //letters and underscores for 0xff, 0b1010 and 1_000, parse_int checks them
+                token_length += stream.count_bytes_while(|c| c.is_ascii_alphanumeric() || c == '_');
                 if let Some('.') = stream.next() {
-                    let after_dot = stream.count_bytes_while(|c| matches!(c, '0'..='9'));
+                    let after_dot = stream.count_bytes_while(|c| c.is_ascii_digit());
                     if after_dot == 0 {
-                        //dot isn't supposed to be part of number, wether this represents an error get's handled by the parser
+                        //dot isn't supposed to be part of number, it starts a member or is an
+                        //error the parser reports
                         let slice_num = &s[i_bytes..i_bytes + token_length];
+                        let token_num = Token::at(TokenType::Number, slice_num, i_bytes);
                         i_bytes += token_length;
-                        let token_num = Token::new(TokenType::Number, slice_num);
                         tokens.push(token_num);
-                        let slice_dot = &s[i_bytes..i_bytes + 1];
-                        i_bytes += 1;
-                        let token_dot = Token::new(TokenType::Single, slice_dot);
+                        let dot_length = 1 + stream.count_member_bytes();
+                        let dot_type = match dot_length {
+                            1 => TokenType::Single,
+                            _ => TokenType::Member,
+                        };
+                        let slice_dot = &s[i_bytes..i_bytes + dot_length];
+                        let token_dot = Token::at(dot_type, slice_dot, i_bytes);
+                        i_bytes += dot_length;
                         tokens.push(token_dot);
                         continue;
                     }
@@ -78,19 +95,117 @@ pub fn lex(s: &str) -> Vec<Token> {
                 }
                 token_type = TokenType::Number;
             }
+            '"' | '\'' => {
+                let quote = c;
+                let mut escaped = false;
+                token_length += stream.count_bytes_while(|c| {
+                    let inside = escaped || c != quote;
+                    escaped = !escaped && c == '\\';
+                    inside
+                });
+                //the closing quote, an unterminated string just ends with the file
+                if stream.next() == Some(quote) {
+                    token_length += 1;
+                }
+                token_type = match quote {
+                    '"' => TokenType::Str,
+                    _ => TokenType::Char,
+                };
+            }
             c if c.is_whitespace() => {
                 i_bytes += 1;
                 continue;
             }
+            //`.x` of `p.x`
+            '.' => {
+                let name_length = stream.count_member_bytes();
+                if name_length > 0 {
+                    token_length += name_length;
+                    token_type = TokenType::Member;
+                }
+            }
+            //the arrow in front of a return type
+            '-' => {
+                if let Some('>') = stream.next() {
+                    token_length += 1;
+                } else {
+                    stream.skip_next = true;
+                }
+            }
             _ => (),
         }
         let slice = &s[i_bytes..i_bytes + token_length];
+        let token = Token::at(token_type, slice, i_bytes);
         i_bytes += token_length;
-        let token = Token::new(token_type, slice);
         tokens.push(token);
     }
     tokens
 }
+//contents of a string or char token: quotes removed, \n \t \\ \" and \' resolved
+pub fn unescape_str(token: &str) -> String {
+    let quote = if token.starts_with('\'') { '\'' } else { '"' };
+    let inner = token.strip_prefix(quote).unwrap_or(token);
+    let inner = inner.strip_suffix(quote).unwrap_or(inner);
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+//the other way around, a string literal as it would be written in the source
+pub fn escape_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+//value of an int literal: decimal, 0x hex, 0b binary with _ between the digits, or a char.
+//hex and binary give the bits, so 0xffffffff is -1. the error says what is wrong
+pub fn parse_int(literal: &str) -> Result<i32, String> {
+    if literal.starts_with('\'') {
+        let mut chars = unescape_str(literal).chars().collect::<Vec<_>>();
+        return match (chars.pop(), chars.is_empty()) {
+            (Some(c), true) if c.is_ascii() => Ok(c as i32),
+            (Some(_), true) => Err(format!("`{literal}` is not an ascii character")),
+            _ => Err(format!("`{literal}` must be one character")),
+        };
+    }
+    let digits = literal.replace('_', "");
+    let (digits, radix) = match digits.get(..2) {
+        Some("0x") => (&digits[2..], 16),
+        Some("0b") => (&digits[2..], 2),
+        _ => (&digits[..], 10),
+    };
+    //constant folding can make negative literals
+    let unsigned = digits.strip_prefix('-').filter(|_| radix == 10).unwrap_or(digits);
+    if unsigned.is_empty() || !unsigned.chars().all(|c| c.is_digit(radix)) || literal.ends_with('_')
+    {
+        return Err(format!("`{literal}` is not a number"));
+    }
+    let value = match radix {
+        10 => digits.parse::<i32>().ok(),
+        _ => u32::from_str_radix(digits, radix).ok().map(|bits| bits as i32),
+    };
+    value.ok_or_else(|| format!("`{literal}` doesn't fit into 32 bits"))
+}
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,4 +228,54 @@ mod tests {
         let s2 = String::from(" a + b         \n \t    *           (5 -7) ");
         assert_eq!(lex(&s1), lex(&s2));
     }
+    #[test]
+    fn lexes_members() {
+        let tokens = lex("p.x1.y 1.5 a[0].z 2.w");
+        let types = tokens.iter().map(|t| (t.token_type.clone(), t.slice)).collect::<Vec<_>>();
+        use TokenType::*;
+        assert_eq!(
+            types,
+            [
+                (Word, "p"),
+                (Member, ".x1"),
+                (Member, ".y"),
+                (Number, "1.5"),
+                (Word, "a"),
+                (Single, "["),
+                (Number, "0"),
+                (Single, "]"),
+                (Member, ".z"),
+                (Number, "2"),
+                (Member, ".w"),
+            ]
+        );
+    }
+    #[test]
+    fn lexes_underscores_in_names() {
+        let tokens = lex("read_int()+p.max_1");
+        let slices = tokens.iter().map(|t| t.slice).collect::<Vec<_>>();
+        assert_eq!(slices, ["read_int", "(", ")", "+", "p", ".max_1"]);
+        assert_eq!(tokens[0].token_type, TokenType::Word);
+        assert_eq!(tokens[5].token_type, TokenType::Member);
+    }
+    #[test]
+    fn lexes_strings() {
+        let s = r#"print("a \"b\"\n")"#;
+        let tokens = lex(s);
+        assert_eq!(tokens[2], Token::new(TokenType::Str, r#""a \"b\"\n""#));
+        assert_eq!(unescape_str(tokens[2].slice), "a \"b\"\n");
+        assert_eq!(escape_str("a \"b\"\n"), tokens[2].slice);
+    }
+    #[test]
+    fn parses_int_literals() {
+        let tokens = lex("0xff+0b1010*1_000-'\\''");
+        let slices = tokens.iter().map(|t| t.slice).collect::<Vec<_>>();
+        assert_eq!(slices, ["0xff", "+", "0b1010", "*", "1_000", "-", "'\\''"]);
+        let values = ["0xff", "0b1010", "1_000", "'\\''", "'a'", "0xffffffff", "2147483647", "-4"];
+        assert_eq!(values.map(parse_int), [255, 10, 1000, 39, 97, -1, i32::MAX, -4].map(Ok));
+        assert!(parse_int("2147483648").unwrap_err().contains("doesn't fit"));
+        assert!(parse_int("0x1_0000_0000").unwrap_err().contains("doesn't fit"));
+        assert!(parse_int("0b102").unwrap_err().contains("not a number"));
+        assert!(parse_int("'ab'").unwrap_err().contains("one character"));
+    }
 }
@@ -2,15 +2,60 @@
 pub enum TokenType {
     Number,
     Word,
+    //with the quotes, escapes are still in it
+    Str,
+    //'a', like Str
+    Char,
+    //`.name` after a struct, with the dot
+    Member,
     Single,
 }
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, Eq)]
 pub struct Token<'a> {
     pub token_type: TokenType,
     pub slice: &'a str,
+    //byte offset into the source
+    pub start: usize,
 }
 impl<'a> Token<'a> {
     pub fn new(token_type: TokenType, slice: &'a str) -> Token<'a> {
-        Token { token_type, slice }
+        Self::at(token_type, slice, 0)
+    }
+    pub fn at(token_type: TokenType, slice: &'a str, start: usize) -> Token<'a> {
+        Token {
+            token_type,
+            slice,
+            start,
+        }
+    }
+    pub fn span(&self) -> Span {
+        Span::new(self.start, self.start + self.slice.len())
+    }
+}
+//the position doesn't matter when comparing tokens
+impl PartialEq for Token<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type && self.slice == other.slice
+    }
+}
+//byte range in the source code
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+    pub fn join(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+    //1-based line and column of the start
+    pub fn line_col(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let col = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        (line, col)
     }
 }
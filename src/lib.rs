@@ -0,0 +1,63 @@
+use std::{fs, io, path::Path};
+
+use diagnostic::Diagnostic;
+use expression::CodeBlock;
+use mips::instr::Program;
+use modules::Sources;
+
+pub mod diagnostic;
+pub mod dump;
+pub mod expression;
+pub mod fmt;
+pub mod ir;
+pub mod lexer;
+pub mod mips;
+pub mod modules;
+pub mod opt;
+pub mod parser;
+pub mod pattern;
+pub mod prelude;
+pub mod types;
+
+//parsed, linked with the imported modules and the prelude, checked and simplified, ready to
+//be lowered, with warnings about removed code. type errors stop the compilation
+pub fn front_end(source: &str) -> Result<(CodeBlock, Vec<Diagnostic>), Vec<Diagnostic>> {
+    front_end_in(&mut Sources::new("", source.to_string()), |path| fs::read_to_string(path))
+}
+//front_end for the first file of `sources`, `read` gets the files it imports. they are added
+//to `sources` for rendering the diagnostics
+pub fn front_end_in(
+    sources: &mut Sources,
+    read: impl Fn(&Path) -> io::Result<String>,
+) -> Result<(CodeBlock, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let mut ast = modules::load(sources, read)?;
+    prelude::link(&mut ast);
+    let errors = types::check(&mut ast);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let warnings = opt::simplify(&mut ast);
+    Ok((ast, warnings))
+}
+//three address code with the optimizations that need it, `bounds_checks` makes indexing
+//outside of an array stop the program (--check-bounds)
+pub fn middle_end(ast: &CodeBlock, bounds_checks: bool) -> ir::Module {
+    let mut module = ir::lower::lower(ast, bounds_checks);
+    ir::tail_call::eliminate_tail_calls(&mut module);
+    ir::loops::optimize_loops(&mut module);
+    module
+}
+pub fn lower_source(source: &str) -> Result<ir::Module, Vec<Diagnostic>> {
+    Ok(middle_end(&front_end(source)?.0, false))
+}
+//source code to mips, `optimize` turns on the peephole optimizer (-O)
+pub fn compile(source: &str, optimize: bool) -> Result<Program, Vec<Diagnostic>> {
+    Ok(back_end(&lower_source(source)?, optimize))
+}
+pub fn back_end(module: &ir::Module, optimize: bool) -> Program {
+    let mut program = ir::codegen::gen_program(module);
+    if optimize {
+        program.text = mips::peephole::optimize(program.text);
+    }
+    program
+}
@@ -1,28 +1,16 @@
-use std::rc::Rc;
+use std::{env, fs, io::Read, path::Path, process};
 
-use expression::statements::TwoSideOp;
-use parser::parse;
+use compiler::{
+    back_end, dump,
+    fmt::format,
+    front_end_in,
+    ir::cfg,
+    middle_end,
+    modules::Sources,
+    parser::{default_builders, parse},
+};
 
-use crate::expression::{statements::{FunctionCall, PrintFn, VarDecl, IfBlock, WhileBlock, FuncDecl}, Expression};
-mod expression;
-mod lexer;
-mod mips;
-mod parser;
-mod pattern;
-
-fn main() {
-    let builders = vec![
-        TwoSideOp::get_builder("*", "mult $t0, $t1\nmflo $t0"),
-        TwoSideOp::get_builder("+", "add $t0, $t0, $t1"),
-        TwoSideOp::get_builder("<", "slt $t0, $t1, $t0"),
-        VarDecl::get_builder(),
-        IfBlock::get_builder(),
-        WhileBlock::get_builder(),
-        FunctionCall::get_builder(Rc::new(PrintFn)),
-        FuncDecl::get_builder(),
-        FunctionCall::get_builder_var(),
-    ];
-    let s = "
+const SAMPLE: &str = "
     x sei 5;
     def a(v){
         x sei 3;
@@ -31,12 +19,100 @@ fn main() {
     }
     a(3);
     print(x+2);
-    ".to_string();
-    //let s = "print(3<2)".to_string();
-    let ast = parse(s, &builders);
-    let frame = ast.frame.as_ref().unwrap();
-    println!("ast: {:#?}", ast);
-    println!("addi $sp, $sp, -1000");
-    println!("{}", frame.gen_mips());
-    println!("{}", ast.gen_mips())
+    ";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("fmt") => run_fmt(&args[1..]),
+        _ => run_compile(&args),
+    }
+}
+//compiler [-O] [--check-bounds] [--emit mips|ir|cfg|ast|json] [file]
+fn run_compile(args: &[String]) {
+    let mut emit = "mips";
+    let mut optimize = false;
+    let mut bounds_checks = false;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--emit" => {
+                emit = args
+                    .next()
+                    .map(String::as_str)
+                    .unwrap_or_else(|| fail("--emit needs a value"))
+            }
+            "-O" => optimize = true,
+            "--check-bounds" => bounds_checks = true,
+            flag if flag.starts_with('-') => fail(&format!("unknown option {flag}")),
+            file => path = Some(file),
+        }
+    }
+    //without a file the sample program above gets compiled
+    let s = match path {
+        Some(path) => read_file(path),
+        None => SAMPLE.to_string(),
+    };
+    match emit {
+        "ast" => print!("{}", dump::tree(&parse(s, &default_builders()))),
+        "json" => println!("{}", dump::json(&parse(s, &default_builders()))),
+        "ir" | "cfg" | "mips" => {
+            //imports are read relative to the file
+            let mut sources = Sources::new(path.map_or("<sample>", |p| p), s);
+            let read = |path: &Path| fs::read_to_string(path);
+            let (ast, warnings) = front_end_in(&mut sources, read).unwrap_or_else(|errors| {
+                for error in &errors {
+                    eprint!("{}", sources.render(error));
+                }
+                process::exit(1)
+            });
+            for warning in &warnings {
+                eprint!("{}", sources.render(warning));
+            }
+            let module = middle_end(&ast, bounds_checks);
+            match emit {
+                "ir" => print!("{module}"),
+                "cfg" => print!("{}", cfg::dot(&module)),
+                _ => print!("{}", back_end(&module, optimize)),
+            }
+        }
+        other => fail(&format!("unknown --emit value {other}")),
+    }
+}
+//compiler fmt [--check] [file]
+//formats the file in place, or stdin to stdout if no file is given
+fn run_fmt(args: &[String]) {
+    let check = args.iter().any(|a| a == "--check");
+    let path = args.iter().find(|a| !a.starts_with("--"));
+    let source = match path {
+        Some(path) => read_file(path),
+        None => {
+            let mut s = String::new();
+            std::io::stdin()
+                .read_to_string(&mut s)
+                .unwrap_or_else(|e| fail(&format!("could not read stdin: {e}")));
+            s
+        }
+    };
+    let formatted = format(&parse(source.clone(), &default_builders()));
+    if check {
+        if formatted != source {
+            eprintln!("{} is not formatted", path.map_or("<stdin>", |p| p.as_str()));
+            process::exit(1);
+        }
+        return;
+    }
+    match path {
+        Some(path) => fs::write(path, formatted)
+            .unwrap_or_else(|e| fail(&format!("could not write {path}: {e}"))),
+        None => print!("{formatted}"),
+    }
+}
+fn read_file(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| fail(&format!("could not read {path}: {e}")))
+}
+fn fail(msg: &str) -> ! {
+    eprintln!("error: {msg}");
+    process::exit(1)
 }
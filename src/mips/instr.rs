@@ -0,0 +1,231 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Reg {
+    Zero,
+    At,
+    V0,
+    V1,
+    A0,
+    A1,
+    A2,
+    A3,
+    T0,
+    T1,
+    T2,
+    T3,
+    T4,
+    T5,
+    T6,
+    T7,
+    S0,
+    S1,
+    S2,
+    S3,
+    S4,
+    S5,
+    S6,
+    S7,
+    T8,
+    T9,
+    K0,
+    K1,
+    Gp,
+    Sp,
+    Fp,
+    Ra,
+}
+const REG_NAMES: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+    "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp",
+    "fp", "ra",
+];
+impl Reg {
+    //register number as used by the hardware
+    pub fn number(self) -> usize {
+        self as usize
+    }
+}
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${}", REG_NAMES[self.number()])
+    }
+}
+
+//coprocessor 1 register, $f0..$f31
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FReg(pub u8);
+impl fmt::Display for FReg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$f{}", self.0)
+    }
+}
+
+pub type Label = String;
+
+//the subset of mips the code generator uses, loads and stores are `reg, offset(base)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instr {
+    Add(Reg, Reg, Reg),
+    Addi(Reg, Reg, i32),
+    Addu(Reg, Reg, Reg),
+    //upper 16 bits, the lower ones get cleared
+    Lui(Reg, i32),
+    Ori(Reg, Reg, i32),
+    Sub(Reg, Reg, Reg),
+    Mult(Reg, Reg),
+    Div(Reg, Reg),
+    Mflo(Reg),
+    Mfhi(Reg),
+    Slt(Reg, Reg, Reg),
+    Sll(Reg, Reg, i32),
+    Sllv(Reg, Reg, Reg),
+    Lb(Reg, i32, Reg),
+    Sb(Reg, i32, Reg),
+    Lw(Reg, i32, Reg),
+    Sw(Reg, i32, Reg),
+    La(Reg, Label),
+    Mtc1(Reg, FReg),
+    Mfc1(Reg, FReg),
+    AddS(FReg, FReg, FReg),
+    SubS(FReg, FReg, FReg),
+    MulS(FReg, FReg, FReg),
+    DivS(FReg, FReg, FReg),
+    //sets the fpu condition flag
+    CLtS(FReg, FReg),
+    //moves if the condition flag is false
+    Movf(Reg, Reg),
+    CvtSW(FReg, FReg),
+    TruncWS(FReg, FReg),
+    Beqz(Reg, Label),
+    J(Label),
+    Jal(Label),
+    Jr(Reg),
+    Syscall,
+}
+impl Instr {
+    //label this instruction may jump to
+    pub fn target(&self) -> Option<&str> {
+        match self {
+            Instr::Beqz(_, label) | Instr::J(label) | Instr::Jal(label) => Some(label),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instr::Add(d, s, t) => write!(f, "add {d}, {s}, {t}"),
+            Instr::Addi(d, s, imm) => write!(f, "addi {d}, {s}, {imm}"),
+            Instr::Addu(d, s, t) => write!(f, "addu {d}, {s}, {t}"),
+            Instr::Lui(d, imm) => write!(f, "lui {d}, {imm}"),
+            Instr::Ori(d, s, imm) => write!(f, "ori {d}, {s}, {imm}"),
+            Instr::Sub(d, s, t) => write!(f, "sub {d}, {s}, {t}"),
+            Instr::Mult(s, t) => write!(f, "mult {s}, {t}"),
+            Instr::Div(s, t) => write!(f, "div {s}, {t}"),
+            Instr::Mflo(d) => write!(f, "mflo {d}"),
+            Instr::Mfhi(d) => write!(f, "mfhi {d}"),
+            Instr::Slt(d, s, t) => write!(f, "slt {d}, {s}, {t}"),
+            Instr::Sll(d, s, shamt) => write!(f, "sll {d}, {s}, {shamt}"),
+            Instr::Sllv(d, s, t) => write!(f, "sllv {d}, {s}, {t}"),
+            Instr::Lb(r, off, base) => write!(f, "lb {r}, {off}({base})"),
+            Instr::Sb(r, off, base) => write!(f, "sb {r}, {off}({base})"),
+            Instr::Lw(r, off, base) => write!(f, "lw {r}, {off}({base})"),
+            Instr::Sw(r, off, base) => write!(f, "sw {r}, {off}({base})"),
+            Instr::La(r, label) => write!(f, "la {r}, {label}"),
+            Instr::Mtc1(r, fr) => write!(f, "mtc1 {r}, {fr}"),
+            Instr::Mfc1(r, fr) => write!(f, "mfc1 {r}, {fr}"),
+            Instr::AddS(d, s, t) => write!(f, "add.s {d}, {s}, {t}"),
+            Instr::SubS(d, s, t) => write!(f, "sub.s {d}, {s}, {t}"),
+            Instr::MulS(d, s, t) => write!(f, "mul.s {d}, {s}, {t}"),
+            Instr::DivS(d, s, t) => write!(f, "div.s {d}, {s}, {t}"),
+            Instr::CLtS(s, t) => write!(f, "c.lt.s {s}, {t}"),
+            Instr::Movf(d, s) => write!(f, "movf {d}, {s}"),
+            Instr::CvtSW(d, s) => write!(f, "cvt.s.w {d}, {s}"),
+            Instr::TruncWS(d, s) => write!(f, "trunc.w.s {d}, {s}"),
+            Instr::Beqz(r, label) => write!(f, "beqz {r}, {label}"),
+            Instr::J(label) => write!(f, "j {label}"),
+            Instr::Jal(label) => write!(f, "jal {label}"),
+            Instr::Jr(r) => write!(f, "jr {r}"),
+            Instr::Syscall => write!(f, "syscall"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Instr(Instr),
+    Label(Label),
+    Comment(String),
+}
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Instr(instr) => write!(f, "{instr}"),
+            Item::Label(label) => write!(f, "{label}:"),
+            Item::Comment(comment) => write!(f, "#{comment}"),
+        }
+    }
+}
+impl From<Instr> for Item {
+    fn from(instr: Instr) -> Self {
+        Item::Instr(instr)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataValue {
+    Word(Vec<i32>),
+    Float(Vec<f32>),
+    Asciiz(String),
+    Space(usize),
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataItem {
+    pub label: Label,
+    pub value: DataValue,
+}
+impl fmt::Display for DataItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.label)?;
+        match &self.value {
+            DataValue::Word(words) => {
+                let words = words.iter().map(i32::to_string).collect::<Vec<_>>();
+                write!(f, ".word {}", words.join(", "))
+            }
+            DataValue::Float(floats) => {
+                let floats = floats.iter().map(|x| format!("{x:?}")).collect::<Vec<_>>();
+                write!(f, ".float {}", floats.join(", "))
+            }
+            DataValue::Asciiz(s) => write!(f, ".asciiz \"{}\"", s.escape_default()),
+            DataValue::Space(n) => write!(f, ".space {n}"),
+        }
+    }
+}
+
+//a whole assembly file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Program {
+    pub data: Vec<DataItem>,
+    pub text: Vec<Item>,
+}
+impl Program {
+    pub fn new(text: Vec<Item>) -> Self {
+        Self { data: vec![], text }
+    }
+}
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.data.is_empty() {
+            writeln!(f, ".data")?;
+            for item in &self.data {
+                writeln!(f, "{item}")?;
+            }
+        }
+        writeln!(f, ".text")?;
+        for item in &self.text {
+            writeln!(f, "{item}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,11 @@
+pub mod instr;
+pub mod peephole;
+#[cfg(test)]
+pub mod sim;
+
+use self::instr::Item;
+
+pub type Code = Vec<Item>;
+
+//label the program starts at
+pub const ENTRY: &str = "main";
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use super::{
+    instr::{Instr, Item, Reg},
+    Code, ENTRY,
+};
+
+pub fn optimize(code: Code) -> Code {
+    let mut code = code;
+    loop {
+        let (new_code, changed) = rewrite_pass(code);
+        code = remove_unused_labels(new_code);
+        if !changed {
+            break;
+        }
+    }
+    code
+}
+
+//longest rewrite looks at a store and the following load
+const WINDOW: usize = 2;
+fn rewrite_pass(code: Code) -> (Code, bool) {
+    let mut out = Vec::with_capacity(code.len());
+    let mut changed = false;
+    let mut i = 0;
+    while i < code.len() {
+        if let Item::Instr(_) = &code[i] {
+            //the following instructions, comments are skipped, labels end the window
+            let mut window = vec![];
+            let mut next_label = None;
+            for (j, item) in code.iter().enumerate().skip(i) {
+                match item {
+                    Item::Comment(_) => continue,
+                    Item::Label(label) => {
+                        if window.len() == 1 {
+                            next_label = Some(label.as_str());
+                        }
+                        break;
+                    }
+                    Item::Instr(instr) if window.len() < WINDOW => window.push((j, instr)),
+                    Item::Instr(_) => break,
+                }
+            }
+            let instrs = window.iter().map(|(_, instr)| *instr).collect::<Vec<_>>();
+            if let Some((consumed, replacement)) = rewrite(&instrs, next_label) {
+                out.extend(replacement.into_iter().map(Item::Instr));
+                let last = window[consumed - 1].0;
+                //keep the comments that were in between
+                out.extend(
+                    code[i..=last]
+                        .iter()
+                        .filter(|item| matches!(item, Item::Comment(_)))
+                        .cloned(),
+                );
+                i = last + 1;
+                changed = true;
+                continue;
+            }
+        }
+        out.push(code[i].clone());
+        i += 1;
+    }
+    (out, changed)
+}
+
+//returns how many instructions were consumed and what replaces them
+fn rewrite(instrs: &[&Instr], next_label: Option<&str>) -> Option<(usize, Vec<Instr>)> {
+    //store followed by a load of the same word: the value is still in the register
+    if let [Instr::Sw(stored, off, base), Instr::Lw(loaded, off2, base2), ..] = instrs {
+        if off == off2 && base == base2 && stored != base {
+            let mut replacement = vec![Instr::Sw(*stored, *off, *base)];
+            replacement.extend(move_reg(*loaded, *stored));
+            return Some((2, replacement));
+        }
+    }
+    match instrs.first()? {
+        Instr::Add(dst, src, Reg::Zero) if dst == src => Some((1, vec![])),
+        Instr::Addi(dst, src, 0) if dst == src => Some((1, vec![])),
+        //jump to the very next line
+        Instr::J(target) if Some(target.as_str()) == next_label => Some((1, vec![])),
+        _ => None,
+    }
+}
+fn move_reg(dst: Reg, src: Reg) -> Vec<Instr> {
+    if dst == src {
+        vec![]
+    } else {
+        vec![Instr::Add(dst, src, Reg::Zero)]
+    }
+}
+fn remove_unused_labels(code: Code) -> Code {
+    let used = code
+        .iter()
+        .filter_map(|item| match item {
+            Item::Instr(instr) => instr.target().map(str::to_string),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    code.into_iter()
+        .filter(
+            |item| !matches!(item, Item::Label(label) if !used.contains(label) && label != ENTRY),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(s: &str) -> Item {
+        Item::Comment(s.to_string())
+    }
+    fn label(s: &str) -> Item {
+        Item::Label(s.to_string())
+    }
+
+    #[test]
+    fn forwards_stores_to_loads() {
+        let code = vec![
+            Instr::Sw(Reg::T0, -4, Reg::Fp).into(),
+            comment("next"),
+            Instr::Lw(Reg::T0, -4, Reg::Fp).into(),
+            Instr::Sw(Reg::T0, -8, Reg::Fp).into(),
+            Instr::Lw(Reg::T1, -8, Reg::Fp).into(),
+            Instr::Lw(Reg::T1, -12, Reg::Fp).into(),
+        ];
+        assert_eq!(
+            optimize(code),
+            vec![
+                Instr::Sw(Reg::T0, -4, Reg::Fp).into(),
+                comment("next"),
+                Instr::Sw(Reg::T0, -8, Reg::Fp).into(),
+                Instr::Add(Reg::T1, Reg::T0, Reg::Zero).into(),
+                Instr::Lw(Reg::T1, -12, Reg::Fp).into()
+            ]
+        );
+    }
+    #[test]
+    fn removes_redundant_moves_jumps_and_labels() {
+        let code = vec![
+            label(ENTRY),
+            Instr::Add(Reg::T0, Reg::T0, Reg::Zero).into(),
+            Instr::J("end".to_string()).into(),
+            comment("comment"),
+            label("end"),
+            label("unused"),
+            Instr::Beqz(Reg::T0, "loop".to_string()).into(),
+            label("loop"),
+            Instr::Add(Reg::A0, Reg::T0, Reg::Zero).into(),
+        ];
+        assert_eq!(
+            optimize(code),
+            vec![
+                label(ENTRY),
+                comment("comment"),
+                Instr::Beqz(Reg::T0, "loop".to_string()).into(),
+                label("loop"),
+                Instr::Add(Reg::A0, Reg::T0, Reg::Zero).into()
+            ]
+        );
+    }
+}
@@ -0,0 +1,418 @@
+//small interpreter for the generated programs, only used by the tests
+use std::collections::{HashMap, VecDeque};
+
+use super::instr::{DataValue, FReg, Instr, Item, Program, Reg};
+
+const TEXT_BASE: u32 = 0x0040_0000;
+const DATA_BASE: u32 = 0x1001_0000;
+const HEAP_BASE: u32 = 0x1004_0000;
+const STACK_TOP: u32 = 0x7fff_effc;
+const MAX_STEPS: usize = 10_000_000;
+
+pub struct Machine<'a> {
+    instrs: Vec<&'a Instr>,
+    labels: HashMap<&'a str, u32>,
+    regs: [i32; 32],
+    fregs: [f32; 32],
+    //fpu condition flag
+    fcc: bool,
+    hi: i32,
+    lo: i32,
+    mem: HashMap<u32, u8>,
+    heap_end: u32,
+    input: VecDeque<String>,
+    pub output: String,
+}
+impl<'a> Machine<'a> {
+    pub fn new(program: &'a Program, input: &str) -> Self {
+        let mut labels = HashMap::new();
+        let mut instrs = vec![];
+        for item in &program.text {
+            match item {
+                Item::Instr(instr) => instrs.push(instr),
+                Item::Label(label) => {
+                    labels.insert(label.as_str(), TEXT_BASE + 4 * instrs.len() as u32);
+                }
+                Item::Comment(_) => (),
+            }
+        }
+        let mut machine = Self {
+            instrs,
+            labels,
+            regs: [0; 32],
+            fregs: [0.0; 32],
+            fcc: false,
+            hi: 0,
+            lo: 0,
+            mem: HashMap::new(),
+            heap_end: HEAP_BASE,
+            input: input.lines().map(str::to_string).collect(),
+            output: String::new(),
+        };
+        let mut addr = DATA_BASE;
+        for item in &program.data {
+            machine.labels.insert(item.label.as_str(), addr);
+            match &item.value {
+                DataValue::Word(words) => {
+                    addr = (addr + 3) & !3;
+                    machine.labels.insert(item.label.as_str(), addr);
+                    for word in words {
+                        machine.store_word(addr, *word);
+                        addr += 4;
+                    }
+                }
+                DataValue::Float(floats) => {
+                    addr = (addr + 3) & !3;
+                    machine.labels.insert(item.label.as_str(), addr);
+                    for x in floats {
+                        machine.store_word(addr, x.to_bits() as i32);
+                        addr += 4;
+                    }
+                }
+                DataValue::Asciiz(s) => {
+                    for b in s.bytes().chain([0]) {
+                        machine.mem.insert(addr, b);
+                        addr += 1;
+                    }
+                }
+                DataValue::Space(n) => addr += *n as u32,
+            }
+        }
+        machine.set(Reg::Sp, STACK_TOP as i32);
+        machine
+    }
+    fn get(&self, reg: Reg) -> i32 {
+        self.regs[reg.number()]
+    }
+    fn set(&mut self, reg: Reg, value: i32) {
+        if reg != Reg::Zero {
+            self.regs[reg.number()] = value;
+        }
+    }
+    fn fget(&self, reg: FReg) -> f32 {
+        self.fregs[reg.0 as usize]
+    }
+    fn addr(&self, off: i32, base: Reg) -> u32 {
+        self.get(base).wrapping_add(off) as u32
+    }
+    fn load_byte(&self, addr: u32) -> u8 {
+        *self.mem.get(&addr).unwrap_or(&0)
+    }
+    fn load_word(&self, addr: u32) -> i32 {
+        assert_eq!(addr % 4, 0, "unaligned word access at {addr:#x}");
+        i32::from_le_bytes([0, 1, 2, 3].map(|i| self.load_byte(addr + i)))
+    }
+    fn store_word(&mut self, addr: u32, value: i32) {
+        assert_eq!(addr % 4, 0, "unaligned word access at {addr:#x}");
+        for (i, b) in value.to_le_bytes().into_iter().enumerate() {
+            self.mem.insert(addr + i as u32, b);
+        }
+    }
+    fn jump(&self, label: &str) -> usize {
+        let addr = self.labels[label];
+        ((addr - TEXT_BASE) / 4) as usize
+    }
+    fn read_line(&mut self) -> String {
+        self.input.pop_front().expect("program wants more input")
+    }
+    pub fn run(&mut self) {
+        let mut pc = 0;
+        for _ in 0..MAX_STEPS {
+            let Some(instr) = self.instrs.get(pc).copied() else {
+                return;
+            };
+            pc += 1;
+            match instr {
+                Instr::Add(d, s, t) => {
+                    let value = self.get(*s).checked_add(self.get(*t));
+                    self.set(*d, value.expect("arithmetic overflow"));
+                }
+                Instr::Addi(d, s, imm) => {
+                    let fits = i16::try_from(*imm).is_ok();
+                    assert!(fits, "immediate {imm} doesn't fit into 16 bits");
+                    let value = self.get(*s).checked_add(*imm);
+                    self.set(*d, value.expect("arithmetic overflow"));
+                }
+                Instr::Lui(d, imm) => self.set(*d, imm << 16),
+                Instr::Ori(d, s, imm) => self.set(*d, self.get(*s) | (imm & 0xffff)),
+                Instr::Addu(d, s, t) => self.set(*d, self.get(*s).wrapping_add(self.get(*t))),
+                Instr::Sub(d, s, t) => {
+                    let value = self.get(*s).checked_sub(self.get(*t));
+                    self.set(*d, value.expect("arithmetic overflow"));
+                }
+                Instr::Mult(s, t) => {
+                    let product = self.get(*s) as i64 * self.get(*t) as i64;
+                    self.lo = product as i32;
+                    self.hi = (product >> 32) as i32;
+                }
+                Instr::Div(s, t) => {
+                    //the result of a division by zero is undefined, lo and hi stay as they are
+                    if self.get(*t) != 0 {
+                        self.lo = self.get(*s).wrapping_div(self.get(*t));
+                        self.hi = self.get(*s).wrapping_rem(self.get(*t));
+                    }
+                }
+                Instr::Mflo(d) => self.set(*d, self.lo),
+                Instr::Mfhi(d) => self.set(*d, self.hi),
+                Instr::Slt(d, s, t) => self.set(*d, (self.get(*s) < self.get(*t)) as i32),
+                Instr::Sll(d, s, shamt) => self.set(*d, self.get(*s) << shamt),
+                //only the low five bits of the amount count
+                Instr::Sllv(d, s, t) => self.set(*d, self.get(*s).wrapping_shl(self.get(*t) as u32)),
+                Instr::Lb(r, off, base) => {
+                    let value = self.load_byte(self.addr(*off, *base)) as i8;
+                    self.set(*r, value as i32);
+                }
+                Instr::Sb(r, off, base) => {
+                    let addr = self.addr(*off, *base);
+                    self.mem.insert(addr, self.get(*r) as u8);
+                }
+                Instr::Lw(r, off, base) => self.set(*r, self.load_word(self.addr(*off, *base))),
+                Instr::Sw(r, off, base) => self.store_word(self.addr(*off, *base), self.get(*r)),
+                Instr::La(r, label) => self.set(*r, self.labels[label.as_str()] as i32),
+                Instr::Mtc1(r, fr) => {
+                    self.fregs[fr.0 as usize] = f32::from_bits(self.get(*r) as u32);
+                }
+                Instr::Mfc1(r, fr) => self.set(*r, self.fregs[fr.0 as usize].to_bits() as i32),
+                Instr::AddS(d, s, t) => self.fregs[d.0 as usize] = self.fget(*s) + self.fget(*t),
+                Instr::SubS(d, s, t) => self.fregs[d.0 as usize] = self.fget(*s) - self.fget(*t),
+                Instr::MulS(d, s, t) => self.fregs[d.0 as usize] = self.fget(*s) * self.fget(*t),
+                Instr::DivS(d, s, t) => self.fregs[d.0 as usize] = self.fget(*s) / self.fget(*t),
+                Instr::CLtS(s, t) => self.fcc = self.fget(*s) < self.fget(*t),
+                Instr::Movf(d, s) => {
+                    if !self.fcc {
+                        self.set(*d, self.get(*s));
+                    }
+                }
+                Instr::CvtSW(d, s) => {
+                    self.fregs[d.0 as usize] = self.fget(*s).to_bits() as i32 as f32;
+                }
+                //out of range values give the largest int like the hardware does
+                Instr::TruncWS(d, s) => {
+                    let value = self.fget(*s).trunc();
+                    let int = if value.is_nan() || !(-2147483648.0..2147483648.0).contains(&value) {
+                        i32::MAX
+                    } else {
+                        value as i32
+                    };
+                    self.fregs[d.0 as usize] = f32::from_bits(int as u32);
+                }
+                Instr::Beqz(r, label) => {
+                    if self.get(*r) == 0 {
+                        pc = self.jump(label);
+                    }
+                }
+                Instr::J(label) => pc = self.jump(label),
+                Instr::Jal(label) => {
+                    self.set(Reg::Ra, (TEXT_BASE + 4 * pc as u32) as i32);
+                    pc = self.jump(label);
+                }
+                Instr::Jr(r) => pc = ((self.get(*r) as u32 - TEXT_BASE) / 4) as usize,
+                Instr::Syscall => {
+                    if !self.syscall() {
+                        return;
+                    }
+                }
+            }
+        }
+        panic!("program did not stop after {MAX_STEPS} steps");
+    }
+    //returns false if the program should stop
+    fn syscall(&mut self) -> bool {
+        let a0 = self.get(Reg::A0);
+        match self.get(Reg::V0) {
+            1 => self.output.push_str(&a0.to_string()),
+            2 => self.output.push_str(&format!("{:?}", self.fregs[12])),
+            4 => {
+                let mut addr = a0 as u32;
+                while self.load_byte(addr) != 0 {
+                    self.output.push(self.load_byte(addr) as char);
+                    addr += 1;
+                }
+            }
+            5 => {
+                let line = self.read_line();
+                let value = line.trim().parse().expect("read_int got no number");
+                self.set(Reg::V0, value);
+            }
+            8 => {
+                let line = self.read_line() + "\n";
+                let len = self.get(Reg::A1).max(1) as usize;
+                let bytes = line.bytes().take(len - 1).chain([0]);
+                for (i, b) in bytes.enumerate() {
+                    self.mem.insert(a0 as u32 + i as u32, b);
+                }
+            }
+            9 => {
+                let addr = self.heap_end;
+                self.heap_end = (self.heap_end + a0 as u32 + 3) & !3;
+                self.set(Reg::V0, addr as i32);
+            }
+            10 => return false,
+            12 => {
+                let c = self.read_line().bytes().next().unwrap_or(b'\n');
+                self.set(Reg::V0, c as i32);
+            }
+            code => panic!("unknown syscall {code}"),
+        }
+        true
+    }
+}
+
+pub fn run(program: &Program, input: &str) -> String {
+    let mut machine = Machine::new(program, input);
+    machine.run();
+    machine.output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile;
+
+    //runs the program with and without optimisations, both have to print the same
+    fn output(s: &str) -> String {
+        let plain = run(&compile(s, false).unwrap(), "");
+        let optimized = run(&compile(s, true).unwrap(), "");
+        assert_eq!(plain, optimized, "optimized program behaves differently");
+        plain
+    }
+
+    #[test]
+    fn runs_arithmetic() {
+        assert_eq!(output("x sei 5; print(x + 2); print(x * 3 - 4 / 2)"), "713");
+    }
+    #[test]
+    fn runs_loops_and_branches() {
+        let s = "i sei 0; while (i < 4) { if (i < 2) { print(i) } i sei i + 1 }";
+        assert_eq!(output(s), "01");
+    }
+    #[test]
+    fn runs_functions() {
+        assert_eq!(output("def f(a, b) { a * b + 1 } print(f(2, 3))"), "7");
+    }
+    #[test]
+    fn runs_recursion() {
+        let s =
+            "def fac(n) { r sei 1; if (1 < n) { r sei n * (fac(n - 1)) } r } print(fac(5))";
+        assert_eq!(output(s), "120");
+    }
+    #[test]
+    fn runs_early_returns() {
+        let s = "def fac(n) { if (n < 2) { return 1 } return n * fac(n - 1) } print(fac(5))";
+        assert_eq!(output(s), "120");
+    }
+    #[test]
+    fn runs_deep_tail_recursion() {
+        let s = "def sum(n, acc) { if (n < 1) { return acc } return sum(n - 1, acc + n) } print(sum(50000, 0))";
+        assert_eq!(output(s), "1250025000");
+    }
+    #[test]
+    fn runs_inlined_functions() {
+        let s = "def sq(x) { x * x } def inc(x) { x sei x + 1; x } print(sq(3) + sq(4)); print(inc(inc(1)))";
+        assert_eq!(output(s), "253");
+    }
+    #[test]
+    fn runs_strings_and_bools() {
+        let s = "def greet(name) { print(\"hi \"); print(name) } greet(\"you\\n\"); b sei 1 < 2; if (b) { print(b) }";
+        assert_eq!(output(s), "hi you\n1");
+    }
+    #[test]
+    fn runs_annotated_functions_before_their_declaration() {
+        let s = "print(name(3 < 4)); def name(b: bool) -> string { if (b) { return \"yes\" } return \"no\" }";
+        assert_eq!(output(s), "yes");
+    }
+    #[test]
+    fn runs_prelude_functions() {
+        let s = "print(abs(0 - 5)); print(min(3, 4)); print(max(3, 4)); print(pow(2, 10)); print(gcd(12, 0 - 18)); print_line(\"ok\")";
+        assert_eq!(output(s), "53410246ok\n");
+    }
+    #[test]
+    fn runs_float_arithmetic() {
+        let s = "def area(r: float) -> float { 3.5 * r * r } a sei area(2.0); print(a); print(int(a) + 1); if (a / 4.0 - 1.0 < float(3)) { print(0.25) }";
+        assert_eq!(output(s), "14.0150.25");
+    }
+    #[test]
+    fn runs_big_and_char_literals() {
+        let s = "x sei 1_000_000; print(x + 0x12345); print(0b1010 + 'a'); print(0xffffffff + 70000)";
+        assert_eq!(output(s), "107456510769999");
+    }
+    #[test]
+    fn runs_arrays() {
+        let s = "
+        a sei [0; 5]; i sei 0;
+        while (i < 5) { a[i] sei i * i; i sei i + 1 }
+        b sei [a[4], a[3] + 1, 7]; print(b[0] + b[1] + b[2]);
+        def sum(xs, n) { s sei 0; j sei 0; while (j < n) { s sei s + xs[j]; j sei j + 1 } s }
+        print(sum(a, 5)); grid sei [[1, 2], [3, 4]]; print(grid[1][0])
+        ";
+        assert_eq!(output(s), "33303");
+    }
+    #[test]
+    fn runs_vectors() {
+        //the second vector grows twice, copying its elements each time
+        let s = "
+        def squares(n) { v sei alloc(0); i sei 0; while (i < n) { push(v, i * i); i sei i + 1 } v }
+        v sei squares(10); print(len(v)); print(v[9]); v[0] sei 7; print(v[0] + v[1]);
+        w sei alloc(3); print(len(w) + w[2]); print(len([1, 2, 3]))
+        ";
+        assert_eq!(output(s), "1081833");
+    }
+    #[test]
+    fn runs_structs() {
+        let s = "
+        struct Point { x, y }
+        def shift(p, d) { p.x sei p.x + d; p }
+        def len2(p) { p.x * p.x + p.y * p.y }
+        ps sei alloc(0); i sei 0;
+        while (i < 3) { push(ps, Point { i, i + 1 }); i sei i + 1 }
+        print(len2(shift(ps[2], 1))); print(ps[0].y); ps[1].y sei 9; print(ps[1].x + ps[1].y)
+        struct Line { a: int, b } l sei Line { 1, ps[2] }; print(l.b.x)
+        ";
+        assert_eq!(output(s), "181103");
+    }
+    #[test]
+    fn runs_reading_input() {
+        let s = "
+        n sei read_int(); sum sei 0; i sei 0;
+        while (i < n) { sum sei sum + read_int(); i sei i + 1 }
+        print(sum); print(read_char()); buf sei buffer(10); read_string(buf, 6); print(buf)
+        ";
+        let input = "3\n1\n2\n3\nx\nhello world\n";
+        for optimize in [false, true] {
+            assert_eq!(run(&compile(s, optimize).unwrap(), input), "6120hello");
+        }
+    }
+    #[test]
+    fn reads_strings_into_buffers() {
+        //the strings of the program stay as they are, they can't be read into
+        let s = "buf sei buffer(10); read_string(buf, 6); print(\"..........\"); print(buf)";
+        for optimize in [false, true] {
+            assert_eq!(run(&compile(s, optimize).unwrap(), "hello\n"), "..........hello");
+        }
+        let s = "buf sei buffer(2); read_string(buf, 2); print(buf); read_string(buf, 10); print(\"xyz\")";
+        for optimize in [false, true] {
+            let output = run(&compile(s, optimize).unwrap(), "overflowing\nab\n");
+            assert_eq!(output, "obuffer too small\n");
+        }
+        assert!(compile("buf sei \"..\"; read_string(buf, 10)", false).is_err());
+    }
+    #[test]
+    fn stops_at_indices_out_of_bounds() {
+        let checked = |s: &str| {
+            let ast = crate::front_end(s).unwrap().0;
+            run(&crate::back_end(&crate::middle_end(&ast, true), true), "")
+        };
+        let s = "def get(a, i) { a[i] } xs sei [1, 2]; print(get(xs, 1)); print(get(xs, 2)); print(3)";
+        assert_eq!(checked(s), "2index out of bounds\n");
+        assert_eq!(checked("xs sei [1, 2]; xs[0 - 1] sei 5; print(1)"), "index out of bounds\n");
+    }
+    #[test]
+    fn runs_with_spills_across_calls() {
+        //more values live across the call than there are registers
+        let defs = (0..20).map(|i| format!("a{i} sei n + {i};")).collect::<String>();
+        let sum = (0..20).map(|i| format!("a{i}")).collect::<Vec<_>>().join(" + ");
+        //g is recursive, so it stays a call
+        let s = format!("def g(x) {{ if (x < 1) {{ return 0 }} 2 + g(x - 1) }} def f(n) {{ {defs} c sei (g(n)); print({sum} + c) }} f(1)");
+        assert_eq!(output(&s), "212");
+    }
+}
@@ -0,0 +1,373 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    diagnostic::Diagnostic,
+    expression::{
+        statements::{FuncDecl, FunctionCall, Import, StructDecl, StructLit},
+        visit::{
+            walk_call_mut, walk_func_decl, walk_func_decl_mut, walk_struct_lit_mut, MutVisitor,
+            Visitor,
+        },
+        CodeBlock, Expression, Node,
+    },
+    ir::Callee,
+    lexer::token::Span,
+    parser::{default_builders, parse, shift_spans},
+    pattern::KEYWORDS,
+};
+
+//a file of the program, its spans start at `start`
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub text: String,
+    pub start: usize,
+}
+//the files of a program one after the other, so a span tells which file it points into
+pub struct Sources {
+    pub files: Vec<SourceFile>,
+}
+impl Sources {
+    pub fn new(path: impl Into<PathBuf>, text: String) -> Self {
+        let path = path.into();
+        Self {
+            files: vec![SourceFile {
+                path,
+                text,
+                start: 0,
+            }],
+        }
+    }
+    //where the spans of the next file start
+    pub fn end(&self) -> usize {
+        let last = self.files.last().unwrap();
+        last.start + last.text.len() + 1
+    }
+    //like Diagnostic::render, with the file the diagnostic points into
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let span = diagnostic.span;
+        let file = self.files.iter().rev().find(|file| file.start <= span.start);
+        let file = file.unwrap_or(&self.files[0]);
+        let mut local = diagnostic.clone();
+        local.span = Span::new(span.start - file.start, span.end.saturating_sub(file.start));
+        local.render(&file.text, &file.path.display().to_string())
+    }
+}
+
+//parses the first file of `sources` and the modules it imports, `read` gets the text of the
+//other files. the functions of a module are called `math.gcd` everywhere, in the labels as
+//well, so they can't collide with those of the program or of other modules. inside their
+//module they are called without its name. the same goes for its structs
+pub fn load(
+    sources: &mut Sources,
+    read: impl Fn(&Path) -> io::Result<String>,
+) -> Result<CodeBlock, Vec<Diagnostic>> {
+    let root = normalize(&sources.files[0].path);
+    let mut program = parse(sources.files[0].text.clone(), &default_builders());
+    let mut loader = Loader {
+        sources,
+        read: &read,
+        stack: vec![root.clone()],
+        names: HashMap::new(),
+        modules: HashMap::new(),
+        lines: vec![],
+        errors: vec![],
+    };
+    loader.link(&mut program, &root, None);
+    program.lines.append(&mut loader.lines);
+    //the prelude comes after all of the files
+    program.span = Span::new(0, loader.sources.end());
+    if !loader.errors.is_empty() {
+        return Err(loader.errors);
+    }
+    Ok(program)
+}
+
+struct Loader<'a> {
+    sources: &'a mut Sources,
+    read: &'a dyn Fn(&Path) -> io::Result<String>,
+    //the files being loaded, each one imports the next
+    stack: Vec<PathBuf>,
+    //the module in each file and what each module declares
+    names: HashMap<PathBuf, String>,
+    modules: HashMap<String, Declared>,
+    //the declarations of the modules, they go behind the program
+    lines: Vec<Box<dyn Expression>>,
+    errors: Vec<Diagnostic>,
+}
+impl Loader<'_> {
+    //the name of the module in `path`, it is loaded the first time it is imported
+    fn module(&mut self, path: PathBuf, import: &Import) -> Option<String> {
+        if let Some(i) = self.stack.iter().position(|file| *file == path) {
+            let cycle = self.stack[i..].iter().chain([&path]).map(|file| file.display());
+            let cycle = cycle.map(|file| file.to_string()).collect::<Vec<_>>().join(" -> ");
+            return self.error(format!("import cycle: {cycle}"), import.span);
+        }
+        if let Some(name) = self.names.get(&path) {
+            return Some(name.clone());
+        }
+        let name = import.module_name();
+        let mut chars = name.chars();
+        let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !KEYWORDS.contains(&name.as_str());
+        if !valid {
+            return self.error(format!("`{name}` can't name a module"), import.span);
+        }
+        if self.names.values().any(|other| *other == name) {
+            let message = format!("another file already is the module `{name}`");
+            return self.error(message, import.span);
+        }
+        let text = match (self.read)(&path) {
+            Ok(text) => text,
+            Err(e) => return self.error(format!("can't read {}: {e}", path.display()), import.span),
+        };
+        let start = self.sources.end();
+        let mut module = parse(text.clone(), &default_builders());
+        shift_spans(&mut module, start);
+        self.sources.files.push(SourceFile {
+            path: path.clone(),
+            text,
+            start,
+        });
+        self.names.insert(path.clone(), name.clone());
+        self.stack.push(path.clone());
+        self.link(&mut module, &path, Some(&name));
+        self.stack.pop();
+        for line in module.lines {
+            match line.node() {
+                Node::FuncDecl(_) | Node::StructDecl(_) => self.lines.push(line),
+                _ => self.errors.push(Diagnostic::error(
+                    "a module can only declare functions and structs",
+                    line.span(),
+                )),
+            }
+        }
+        Some(name)
+    }
+    //loads the imports of a file and gives its functions and structs their final names,
+    //`module` is None for the program itself
+    fn link(&mut self, block: &mut CodeBlock, path: &Path, module: Option<&str>) {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut imports = HashSet::new();
+        for line in std::mem::take(&mut block.lines) {
+            match line.node() {
+                Node::Import(import) => {
+                    let name = self.module(normalize(&dir.join(&import.path)), import);
+                    imports.extend(name);
+                }
+                _ => block.lines.push(line),
+            }
+        }
+        let mut declared = Declared::default();
+        declared.visit_block(block);
+        if let Some(module) = module {
+            self.modules.insert(module.to_string(), declared.clone());
+        }
+        let mut renamer = Renamer {
+            module,
+            declared: &declared,
+            imports: &imports,
+            modules: &self.modules,
+            errors: &mut self.errors,
+        };
+        renamer.visit_block(block);
+    }
+    fn error(&mut self, message: String, span: Span) -> Option<String> {
+        self.errors.push(Diagnostic::error(message, span));
+        None
+    }
+}
+
+#[derive(Default, Clone)]
+struct Declared {
+    funcs: HashSet<String>,
+    structs: HashSet<String>,
+}
+impl Visitor for Declared {
+    fn visit_func_decl(&mut self, decl: &FuncDecl) {
+        self.funcs.insert(decl.name.clone());
+        walk_func_decl(self, decl);
+    }
+    fn visit_struct_decl(&mut self, decl: &StructDecl) {
+        self.structs.insert(decl.name.clone());
+    }
+}
+
+struct Renamer<'a> {
+    module: Option<&'a str>,
+    declared: &'a Declared,
+    imports: &'a HashSet<String>,
+    modules: &'a HashMap<String, Declared>,
+    errors: &'a mut Vec<Diagnostic>,
+}
+impl Renamer<'_> {
+    //the final name of the function or struct (`what`) the file calls `name`, None if it keeps
+    //its name
+    fn resolve(
+        &self,
+        name: &str,
+        what: &str,
+        names: fn(&Declared) -> &HashSet<String>,
+    ) -> Result<Option<String>, String> {
+        match name.split_once('.') {
+            Some((module, _)) if !self.imports.contains(module) => {
+                Err(format!("module `{module}` is not imported"))
+            }
+            Some((module, item)) if !names(&self.modules[module]).contains(item) => {
+                Err(format!("module `{module}` has no {what} `{item}`"))
+            }
+            Some(_) => Ok(None),
+            //the program and the prelude keep their names
+            None => match self.module {
+                Some(module) if names(self.declared).contains(name) => {
+                    Ok(Some(format!("{module}.{name}")))
+                }
+                _ => Ok(None),
+            },
+        }
+    }
+}
+impl MutVisitor for Renamer<'_> {
+    fn visit_func_decl(&mut self, decl: &mut FuncDecl) {
+        if let Some(module) = self.module {
+            decl.name = format!("{module}.{}", decl.name);
+        }
+        walk_func_decl_mut(self, decl);
+    }
+    fn visit_call(&mut self, call: &mut FunctionCall) {
+        walk_call_mut(self, call);
+        let Callee::Func(name) = call.func.callee() else {
+            return;
+        };
+        match self.resolve(&name, "function", |declared| &declared.funcs) {
+            Ok(Some(name)) => call.rename(name),
+            Ok(None) => {}
+            Err(message) => self.errors.push(Diagnostic::error(message, call.span)),
+        }
+    }
+    fn visit_struct_decl(&mut self, decl: &mut StructDecl) {
+        if let Some(module) = self.module {
+            decl.name = format!("{module}.{}", decl.name);
+        }
+    }
+    fn visit_struct_lit(&mut self, lit: &mut StructLit) {
+        walk_struct_lit_mut(self, lit);
+        match self.resolve(&lit.name, "struct", |declared| &declared.structs) {
+            Ok(Some(name)) => lit.name = name,
+            Ok(None) => {}
+            Err(message) => self.errors.push(Diagnostic::error(message, lit.span)),
+        }
+    }
+    fn visit_import(&mut self, import: &mut Import) {
+        let message = "imports have to be at the top level of the file";
+        self.errors.push(Diagnostic::error(message, import.span));
+    }
+}
+
+//`a/../b.src` and `./b.src` are both `b.src`
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for part in path.components() {
+        match part {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(normal.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                normal.pop();
+            }
+            part => normal.push(part),
+        }
+    }
+    normal
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io, path::Path};
+
+    use super::{load, Sources};
+    use crate::{back_end, front_end_in, middle_end, mips::sim::run};
+
+    const FILES: [(&str, &str); 6] = [
+        ("math.src", "def twice(x) { x * 2 } def quad(x) { twice(twice(x)) }"),
+        ("lib/text.src", "use util; def twice(s) { util.shout(s); util.shout(s) }"),
+        ("lib/util.src", "def shout(s: string) { print(s); print(\"!\") }"),
+        ("lib/loop.src", "import \"../lib/loop.src\"; def f() { 1 }"),
+        ("a.src", "struct P { x } def mk(x) { P { x } }"),
+        ("b.src", "struct P { y, z } def mk(y) { P { y, y * 2 } }"),
+    ];
+
+    fn read(path: &Path) -> io::Result<String> {
+        let files: HashMap<_, _> = FILES.into_iter().collect();
+        let text = files.get(path.to_str().unwrap());
+        text.map(|text| text.to_string()).ok_or(io::ErrorKind::NotFound.into())
+    }
+    fn errors(s: &str) -> Vec<String> {
+        let mut sources = Sources::new("main.src", s.to_string());
+        let errors = load(&mut sources, read).err().unwrap_or_default();
+        errors.into_iter().map(|error| error.message).collect()
+    }
+
+    #[test]
+    fn runs_functions_of_modules() {
+        let s = "import \"math.src\"; import \"lib/text.src\"; def twice(x) { x + x }
+            print(math.quad(3)); print(twice(5)); text.twice(\"hey\")";
+        let mut sources = Sources::new("main.src", s.to_string());
+        let (ast, _) = front_end_in(&mut sources, read).unwrap();
+        let program = back_end(&middle_end(&ast, false), false);
+        assert_eq!(run(&program, ""), "1210hey!hey!");
+        assert_eq!(sources.files.len(), 4);
+    }
+    #[test]
+    fn keeps_the_structs_of_modules_apart() {
+        let s = "use a; use b; p sei a.mk(1); q sei b.mk(2); r sei b.P { 3, 4 }; print(p.x + q.z + r.y)";
+        let mut sources = Sources::new("main.src", s.to_string());
+        let (ast, _) = front_end_in(&mut sources, read).unwrap();
+        assert_eq!(run(&back_end(&middle_end(&ast, false), false), ""), "8");
+        let first_error = |s: &str| {
+            let mut sources = Sources::new("main.src", s.to_string());
+            front_end_in(&mut sources, read).err().unwrap()[0].message.clone()
+        };
+        assert_eq!(first_error("use a; P { 1 }"), "there is no struct `P`");
+        assert_eq!(first_error("use a; a.P { 1, 2 }"), "`a.P` has 1 field, found 2");
+        let error = first_error("use a; use b; p sei a.mk(1); p sei b.mk(2)");
+        assert_eq!(error, "`b.mk` returns a.P, found b.P");
+        assert_eq!(
+            errors("use a; a.Q { 1 }; b.P { 1 }"),
+            ["module `a` has no struct `Q`", "module `b` is not imported"]
+        );
+    }
+    #[test]
+    fn reports_module_errors() {
+        assert_eq!(errors("import \"lib/loop.src\""), ["import cycle: lib/loop.src -> lib/loop.src"]);
+        assert_eq!(errors("use nothing"), ["can't read nothing.src: entity not found"]);
+        assert_eq!(
+            errors("use math; math.half(2); util.shout(1)"),
+            ["module `math` has no function `half`", "module `util` is not imported"]
+        );
+        assert_eq!(
+            errors("import \"lib/util.src\"; def f() { use math }"),
+            ["imports have to be at the top level of the file"]
+        );
+    }
+    #[test]
+    fn points_into_the_right_file() {
+        let first_error = |s: &str| {
+            let mut sources = Sources::new("main.src", s.to_string());
+            let errors = front_end_in(&mut sources, read).err().unwrap();
+            (errors[0].message.clone(), sources.render(&errors[0]))
+        };
+        let (message, rendered) = first_error("import \"lib/util.src\";\nutil.shout(1 < 2)");
+        assert_eq!(message, "argument 1 of `util.shout` must be string, found bool");
+        assert!(rendered.contains("--> main.src:2:12"), "{rendered}");
+        //the parameter of `quad` is a bool now, `twice` gets it in math.src
+        let (message, rendered) = first_error("use math; math.quad(1 < 2)");
+        assert_eq!(message, "argument 1 of `math.twice` must be int, found bool");
+        assert!(rendered.contains("--> math.src:1:50"), "{rendered}");
+        assert!(rendered.contains("--> math.src:1:"), "{rendered}");
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use crate::{
+    expression::{
+        statements::{FuncDecl, Number, VarDecl},
+        visit::{walk_block_mut, walk_expr_mut, MutVisitor, Visitor},
+        BlockType, CodeBlock, Expression, Node, NodeMut,
+    },
+    ir::BinOp,
+    lexer::parse_int,
+};
+
+//folds operations on number literals and replaces variables that are only
+//ever set to one constant by that constant, true if anything changed
+pub fn fold_constants(program: &mut CodeBlock) -> bool {
+    let mut any = false;
+    loop {
+        let mut folder = Folder::default();
+        folder.visit_block(program);
+        let mut changed = folder.changed;
+        changed |= propagate(&mut program.lines, &[]);
+        let mut funcs = FuncScopes::default();
+        funcs.visit_block(program);
+        changed |= funcs.changed;
+        if !changed {
+            return any;
+        }
+        any = true;
+    }
+}
+
+//value of a literal, looking through brackets like `(5)`
+pub fn const_value(expr: &dyn Expression) -> Option<i32> {
+    match expr.node() {
+        Node::Number(number) => parse_int(&number.0).ok(),
+        Node::Bool(value) => Some(value.0 as i32),
+        Node::Block(block) if block.block_type == BlockType::Brack && block.lines.len() == 1 => {
+            const_value(block.lines[0].as_ref())
+        }
+        _ => None,
+    }
+}
+
+//None if the result isn't known at compile time (overflow traps, division by zero)
+pub fn eval_op(op: BinOp, a: i32, b: i32) -> Option<i32> {
+    match op {
+        //add and sub trap on overflow, so those have to stay in the code
+        BinOp::Add => a.checked_add(b),
+        BinOp::Sub => a.checked_sub(b),
+        //mflo just keeps the lower 32 bits
+        BinOp::Mul => Some(a.wrapping_mul(b)),
+        BinOp::Div => a.checked_div(b),
+        BinOp::Rem => a.checked_rem(b),
+        BinOp::Lt => Some((a < b) as i32),
+        BinOp::Addu => Some(a.wrapping_add(b)),
+        BinOp::Shl => (0..32).contains(&b).then(|| a.wrapping_shl(b as u32)),
+        //floats are left to the fpu
+        BinOp::AddF | BinOp::SubF | BinOp::MulF | BinOp::DivF | BinOp::LtF => None,
+    }
+}
+
+#[derive(Default)]
+struct Folder {
+    changed: bool,
+}
+impl MutVisitor for Folder {
+    fn visit_expr(&mut self, expr: &mut Box<dyn Expression>) {
+        walk_expr_mut(self, expr);
+        let folded = match expr.node() {
+            Node::BinOp(op) => {
+                match (const_value(op.values.0.as_ref()), const_value(op.values.1.as_ref())) {
+                    (Some(a), Some(b)) => eval_op(op.op, a, b),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        if let Some(value) = folded {
+            *expr = Box::new(Number(value.to_string(), expr.span()));
+            self.changed = true;
+        }
+    }
+}
+
+//runs the propagation for every function body, each has its own frame
+#[derive(Default)]
+struct FuncScopes {
+    changed: bool,
+}
+impl MutVisitor for FuncScopes {
+    fn visit_func_decl(&mut self, decl: &mut FuncDecl) {
+        let params = decl.args.iter().map(|arg| arg.addr).collect::<Vec<_>>();
+        if let NodeMut::Block(body) = decl.body.node_mut() {
+            self.changed |= propagate(&mut body.lines, &params);
+            walk_block_mut(self, body);
+        }
+    }
+}
+
+//a variable is constant if it gets assigned exactly once, directly in this scope
+//and to a literal; all uses after that assignment are replaced
+fn propagate(lines: &mut [Box<dyn Expression>], params: &[usize]) -> bool {
+    let mut counter = AssignCounter::default();
+    for line in lines.iter() {
+        counter.visit_expr(line.as_ref());
+    }
+    let mut changed = false;
+    for i in 0..lines.len() {
+        let (addr, value) = match lines[i].node() {
+            Node::VarDecl(decl) => match const_value(decl.value.as_ref()) {
+                Some(value) if counter.0[&decl.addr] == 1 && !params.contains(&decl.addr) => {
+                    (decl.addr, value)
+                }
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let mut subst = Substitute {
+            addr,
+            value,
+            changed: false,
+        };
+        for line in lines[i + 1..].iter_mut() {
+            subst.visit_expr(line);
+        }
+        changed |= subst.changed;
+    }
+    changed
+}
+
+#[derive(Default)]
+struct AssignCounter(HashMap<usize, usize>);
+impl Visitor for AssignCounter {
+    fn visit_var_decl(&mut self, decl: &VarDecl) {
+        *self.0.entry(decl.addr).or_default() += 1;
+        self.visit_expr(decl.value.as_ref());
+    }
+    //other function, other frame
+    fn visit_func_decl(&mut self, _decl: &FuncDecl) {}
+}
+
+struct Substitute {
+    addr: usize,
+    value: i32,
+    changed: bool,
+}
+impl MutVisitor for Substitute {
+    fn visit_expr(&mut self, expr: &mut Box<dyn Expression>) {
+        if matches!(expr.node(), Node::Var(var) if var.addr == self.addr) {
+            *expr = Box::new(Number(self.value.to_string(), expr.span()));
+            self.changed = true;
+        } else {
+            walk_expr_mut(self, expr);
+        }
+    }
+    fn visit_func_decl(&mut self, _decl: &mut FuncDecl) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fmt::format,
+        parser::{default_builders, parse},
+    };
+
+    fn folded(s: &str) -> String {
+        let mut ast = parse(s.to_string(), &default_builders());
+        fold_constants(&mut ast);
+        format(&ast)
+    }
+
+    #[test]
+    fn folds_arithmetic() {
+        assert_eq!(folded("print(2 * 3 + 1)"), "print(7);\n");
+        assert_eq!(folded("print((1 + 2) * (7 - 3) < 13)"), "print(1);\n");
+        assert_eq!(folded("print(7 / 2 + 7 % 2)"), "print(4);\n");
+    }
+    #[test]
+    fn keeps_runtime_errors() {
+        assert_eq!(folded("print(1 / 0)"), "print(1 / 0);\n");
+        assert_eq!(
+            folded("print(2147483647 + 1)"),
+            "print(2147483647 + 1);\n"
+        );
+        assert_eq!(folded("print(65536 * 65536)"), "print(0);\n");
+    }
+    #[test]
+    fn propagates_constant_vars() {
+        assert_eq!(
+            folded("x sei 4; y sei x * 2; print(y + x)"),
+            "x sei 4;\ny sei 8;\nprint(12);\n"
+        );
+        //assigned twice, so not constant
+        assert_eq!(
+            folded("x sei 4; x sei x + 1; print(x)"),
+            "x sei 4;\nx sei x + 1;\nprint(x);\n"
+        );
+        //parameters are set by the caller
+        assert_eq!(
+            folded("def f(a) { a sei 1; print(a) }"),
+            "def f(a) {\n    a sei 1;\n    print(a);\n}\n"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+
+use crate::{
+    diagnostic::Diagnostic,
+    expression::{
+        statements::{FuncDecl, Var},
+        visit::{walk_block_mut, walk_func_decl, walk_func_decl_mut, MutVisitor, Visitor},
+        BlockType, CodeBlock, Expression, Node,
+    },
+    lexer::token::Span,
+};
+
+use super::const_fold::const_value;
+
+//variables read somewhere, by function name (None is the top level) and slot
+pub type Reads = HashSet<(Option<String>, usize)>;
+
+pub fn collect_reads(program: &CodeBlock) -> Reads {
+    let mut collector = ReadCollector {
+        scope: None,
+        reads: HashSet::new(),
+    };
+    collector.visit_block(program);
+    collector.reads
+}
+struct ReadCollector {
+    scope: Option<String>,
+    reads: Reads,
+}
+impl Visitor for ReadCollector {
+    fn visit_var(&mut self, var: &Var) {
+        self.reads.insert((self.scope.clone(), var.addr));
+    }
+    fn visit_func_decl(&mut self, decl: &FuncDecl) {
+        let outer = self.scope.replace(decl.name.clone());
+        walk_func_decl(self, decl);
+        self.scope = outer;
+    }
+}
+
+//drops statements after a return, branches with a constant false condition, expression
+//statements without side effects and variables that are never read. `source_reads` are the
+//reads before constant propagation, variables only those replaced don't get a warning
+pub fn eliminate_dead_code(
+    program: &mut CodeBlock,
+    source_reads: &Reads,
+    warnings: &mut Vec<Diagnostic>,
+) -> bool {
+    let mut eliminator = Eliminator {
+        scope: None,
+        reads: collect_reads(program),
+        source_reads,
+        warnings,
+        in_body: false,
+        changed: false,
+    };
+    eliminator.visit_block(program);
+    eliminator.changed
+}
+
+struct Eliminator<'a> {
+    scope: Option<String>,
+    reads: Reads,
+    source_reads: &'a Reads,
+    warnings: &'a mut Vec<Diagnostic>,
+    //the next block is a function body, its last line is the return value
+    in_body: bool,
+    changed: bool,
+}
+impl Eliminator<'_> {
+    fn remove(&mut self, message: String, span: Span) {
+        //inlined code has no span, the function itself gets the warnings
+        if span != Span::default() {
+            self.warnings.push(Diagnostic::warning(message, span));
+        }
+        self.changed = true;
+    }
+    //the lines replacing `line`, warnings for everything that goes away
+    fn eliminate(&mut self, line: Box<dyn Expression>, is_value: bool) -> Vec<Box<dyn Expression>> {
+        match line.node() {
+            Node::If(if_block) => match const_value(if_block.cond.as_ref()) {
+                Some(0) => {
+                    self.remove("condition is always false, this never runs".to_string(), line.span());
+                    vec![]
+                }
+                Some(_) => {
+                    self.changed = true;
+                    vec![if_block.code.clone()]
+                }
+                None => vec![line],
+            },
+            Node::While(while_block) if const_value(while_block.cond.as_ref()) == Some(0) => {
+                self.remove("condition is always false, this never runs".to_string(), line.span());
+                vec![]
+            }
+            Node::VarDecl(decl) if !self.reads.contains(&(self.scope.clone(), decl.addr)) => {
+                if self.source_reads.contains(&(self.scope.clone(), decl.addr)) {
+                    self.changed = true;
+                } else {
+                    self.remove(format!("variable `{}` is never read", decl.name), decl.span);
+                }
+                //the value might still do something
+                if is_pure(decl.value.as_ref()) && !is_value {
+                    vec![]
+                } else {
+                    vec![decl.value.clone()]
+                }
+            }
+            _ if is_pure(line.as_ref()) && !is_value => {
+                self.remove("expression has no effect".to_string(), line.span());
+                vec![]
+            }
+            _ => vec![line],
+        }
+    }
+}
+impl MutVisitor for Eliminator<'_> {
+    fn visit_func_decl(&mut self, decl: &mut FuncDecl) {
+        let outer = self.scope.replace(decl.name.clone());
+        self.in_body = true;
+        walk_func_decl_mut(self, decl);
+        self.scope = outer;
+    }
+    fn visit_block(&mut self, block: &mut CodeBlock) {
+        let is_body = std::mem::take(&mut self.in_body);
+        walk_block_mut(self, block);
+        if block.block_type != BlockType::Curl {
+            return;
+        }
+        let lines = std::mem::take(&mut block.lines);
+        let n = lines.len();
+        let mut lines = lines.into_iter().enumerate();
+        while let Some((i, line)) = lines.next() {
+            block.lines.extend(self.eliminate(line, is_body && i + 1 == n));
+            if block.lines.last().is_some_and(|line| always_returns(line.as_ref())) {
+                let rest = lines.map(|(_, line)| line.span()).reduce(|a, b| a.join(b));
+                if let Some(span) = rest {
+                    self.remove("unreachable code after return".to_string(), span);
+                }
+                break;
+            }
+        }
+    }
+}
+
+//evaluating it can't change anything, a trap of the arithmetic doesn't count
+fn is_pure(expr: &dyn Expression) -> bool {
+    match expr.node() {
+        Node::Number(_) | Node::Str(_) | Node::Bool(_) | Node::Var(_) => true,
+        Node::BinOp(op) => is_pure(op.values.0.as_ref()) && is_pure(op.values.1.as_ref()),
+        Node::Array(array) => array.values.iter().all(|value| is_pure(value.as_ref())),
+        Node::Index(index) => is_pure(index.array.as_ref()) && is_pure(index.index.as_ref()),
+        Node::StructLit(lit) => lit.values.iter().all(|value| is_pure(value.as_ref())),
+        Node::Field(field) => is_pure(field.object.as_ref()),
+        Node::Block(block) if block.block_type == BlockType::Brack => {
+            block.lines.iter().all(|line| is_pure(line.as_ref()))
+        }
+        _ => false,
+    }
+}
+fn always_returns(expr: &dyn Expression) -> bool {
+    match expr.node() {
+        Node::Return(_) => true,
+        Node::Block(block) if block.block_type == BlockType::Curl => {
+            block.lines.iter().any(|line| always_returns(line.as_ref()))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fmt::format,
+        opt::simplify,
+        parser::{default_builders, parse},
+    };
+
+    fn eliminated(s: &str) -> (String, Vec<String>) {
+        let mut ast = parse(s.to_string(), &default_builders());
+        let warnings = simplify(&mut ast);
+        let messages = warnings.into_iter().map(|w| w.message).collect();
+        (format(&ast), messages)
+    }
+
+    #[test]
+    fn removes_constant_branches() {
+        let (code, warnings) =
+            eliminated("if (0) { print(1) } while (1 - 1) { print(2) } if (2 < 3) { print(3) }");
+        assert_eq!(code, "{\n    print(3);\n}\n");
+        assert_eq!(warnings.len(), 2);
+    }
+    #[test]
+    fn removes_code_after_return() {
+        let (code, warnings) = eliminated("def f(n) { if (n) { return 1; print(n) } return 2; print(3); n }");
+        assert_eq!(
+            code,
+            "def f(n) {\n    if (n) {\n        return 1;\n    }\n    return 2;\n}\n"
+        );
+        assert_eq!(warnings, ["unreachable code after return"; 2]);
+    }
+    #[test]
+    fn removes_unused_variables_and_pure_statements() {
+        let (code, warnings) = eliminated("def f(a) { x sei a; y sei f(a); a + 1; a } z sei 2; print(z)");
+        assert_eq!(code, "def f(a) {\n    f(a);\n    a;\n}\nprint(2);\n");
+        assert_eq!(
+            warnings,
+            [
+                "variable `x` is never read",
+                "variable `y` is never read",
+                "expression has no effect"
+            ]
+        );
+    }
+    #[test]
+    fn shrinks_the_frame() {
+        let mut ast = parse("a sei 1; b sei 2; c sei 3; print(b + c)".to_string(), &default_builders());
+        assert_eq!(ast.frame.as_ref().unwrap().n_slots(), 3);
+        simplify(&mut ast);
+        assert_eq!(ast.frame.as_ref().unwrap().n_slots(), 0);
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    expression::{
+        statements::{FrameStack, FuncDecl, FunctionCall, Number, Var, VarDecl},
+        visit::{
+            walk_block_mut, walk_call, walk_expr, walk_expr_mut, walk_func_decl_mut,
+            walk_var_decl_mut, MutVisitor, Visitor,
+        },
+        BlockType, CodeBlock, Expression, Node,
+    },
+    ir::Callee,
+    diagnostic::Diagnostic,
+    lexer::token::Span,
+};
+
+//functions with at most this many nodes get inlined even without `inline def`
+pub const INLINE_THRESHOLD: usize = 12;
+
+//replaces calls of small non-recursive functions by their body, true if any call was replaced.
+//the functions stay until `remove_inlined`, the inlined code gets no spans so it doesn't cause
+//warnings. the names of the functions inlined somewhere go into `inlined`
+pub fn inline_functions(program: &mut CodeBlock, inlined: &mut HashSet<String>) -> bool {
+    let mut decls = DeclCollector::default();
+    decls.visit_block(program);
+    let funcs = decls.inlinable();
+    if funcs.is_empty() {
+        return false;
+    }
+    let mut inliner = Inliner {
+        funcs,
+        frame: program.frame.clone().expect("program without frame"),
+        sites: 0,
+        inlined,
+    };
+    inliner.visit_block(program);
+    inliner.sites > 0
+}
+
+//leaves out the inlined functions nothing calls anymore, then the ones only they called
+pub fn remove_inlined(program: &mut CodeBlock, inlined: &HashSet<String>) {
+    loop {
+        let mut calls = CallCollector::default();
+        calls.visit_block(program);
+        let mut remover = Remover {
+            unused: inlined.difference(&calls.0).cloned().collect(),
+            removed: false,
+        };
+        remover.visit_block(program);
+        if !remover.removed {
+            break;
+        }
+    }
+}
+
+//a warning for each `inline def` that can't be inlined
+pub fn warn_not_inlined(program: &CodeBlock, warnings: &mut Vec<Diagnostic>) {
+    let mut decls = DeclCollector::default();
+    decls.visit_block(program);
+    let calls = decls.calls();
+    let mut found = vec![];
+    for (name, decl) in decls.0.iter().filter(|(_, decl)| decl.inline) {
+        if let Some(reason) = obstacle(&calls, name, decl) {
+            let message = format!("`{name}` can't be inlined, {reason}");
+            found.push(Diagnostic::warning(message, decl.span));
+        }
+    }
+    found.sort_by_key(|warning| warning.span.start);
+    warnings.append(&mut found);
+}
+
+#[derive(Default)]
+struct DeclCollector(HashMap<String, FuncDecl>);
+impl Visitor for DeclCollector {
+    fn visit_func_decl(&mut self, decl: &FuncDecl) {
+        self.0.insert(decl.name.clone(), decl.clone());
+        walk_expr(self, decl.body.as_ref());
+    }
+}
+impl DeclCollector {
+    //the functions each function calls
+    fn calls(&self) -> HashMap<String, HashSet<String>> {
+        self.0
+            .iter()
+            .map(|(name, decl)| {
+                let mut collector = CallCollector::default();
+                collector.visit_expr(decl.body.as_ref());
+                (name.clone(), collector.0)
+            })
+            .collect()
+    }
+    fn inlinable(self) -> HashMap<String, FuncDecl> {
+        let calls = self.calls();
+        self.0
+            .into_iter()
+            .filter(|(name, decl)| {
+                let mut counter = NodeCounter::default();
+                counter.visit_expr(decl.body.as_ref());
+                let small = decl.inline || counter.nodes <= INLINE_THRESHOLD;
+                small && obstacle(&calls, name, decl).is_none()
+            })
+            .collect()
+    }
+}
+//why a function can't be inlined however small it is
+fn obstacle(
+    calls: &HashMap<String, HashSet<String>>,
+    name: &str,
+    decl: &FuncDecl,
+) -> Option<&'static str> {
+    let mut counter = NodeCounter::default();
+    counter.visit_expr(decl.body.as_ref());
+    //a return anywhere but at the end would have to leave the caller's code
+    let last_returns = body_lines(decl)
+        .last()
+        .is_some_and(|line| matches!(line.node(), Node::Return(_)));
+    if counter.has_func_decl {
+        Some("it declares functions")
+    } else if counter.returns != last_returns as usize {
+        Some("it returns early")
+    } else if reaches(calls, name, name) {
+        Some("it is recursive")
+    } else {
+        None
+    }
+}
+//whether `from` calls `to`, directly or through other functions
+fn reaches(calls: &HashMap<String, HashSet<String>>, from: &str, to: &str) -> bool {
+    let mut seen = HashSet::new();
+    let mut todo = vec![from];
+    while let Some(name) = todo.pop() {
+        for callee in calls.get(name).into_iter().flatten() {
+            if callee == to {
+                return true;
+            }
+            if seen.insert(callee.as_str()) {
+                todo.push(callee);
+            }
+        }
+    }
+    false
+}
+fn body_lines(decl: &FuncDecl) -> &[Box<dyn Expression>] {
+    match decl.body.node() {
+        Node::Block(body) => &body.lines,
+        _ => std::slice::from_ref(&decl.body),
+    }
+}
+
+#[derive(Default)]
+struct CallCollector(HashSet<String>);
+impl Visitor for CallCollector {
+    fn visit_call(&mut self, call: &FunctionCall) {
+        if let Callee::Func(name) = call.func.callee() {
+            self.0.insert(name);
+        }
+        walk_call(self, call);
+    }
+}
+#[derive(Default)]
+struct NodeCounter {
+    nodes: usize,
+    returns: usize,
+    has_func_decl: bool,
+}
+impl Visitor for NodeCounter {
+    fn visit_expr(&mut self, expr: &dyn Expression) {
+        self.nodes += 1;
+        match expr.node() {
+            Node::Return(_) => self.returns += 1,
+            Node::FuncDecl(_) => self.has_func_decl = true,
+            _ => {}
+        }
+        walk_expr(self, expr);
+    }
+}
+
+struct Inliner<'a> {
+    funcs: HashMap<String, FuncDecl>,
+    //frame of the function the calls are in
+    frame: FrameStack,
+    sites: usize,
+    inlined: &'a mut HashSet<String>,
+}
+impl Inliner<'_> {
+    //the lines the call turns into: the arguments assigned to the parameters, then the body
+    //with its variables moved to fresh slots of the caller
+    fn inline(&mut self, call: &dyn Expression) -> Option<Vec<Box<dyn Expression>>> {
+        let Node::Call(call) = call.node() else {
+            return None;
+        };
+        let Callee::Func(name) = call.func.callee() else {
+            return None;
+        };
+        let decl = self.funcs.get(&name)?;
+        if decl.args.len() != call.args.len() {
+            return None;
+        }
+        let mut remap = Remap {
+            prefix: format!("{name}.{}.", self.sites),
+            slots: HashMap::new(),
+            frame: &self.frame,
+        };
+        let mut lines: Vec<Box<dyn Expression>> = vec![];
+        for (param, arg) in decl.args.iter().zip(&call.args) {
+            let mut param = param.clone();
+            remap.visit_var(&mut param);
+            lines.push(Box::new(VarDecl::new(param.name, param.addr, arg.clone())));
+        }
+        for line in body_lines(decl) {
+            let mut line = match line.node() {
+                Node::Return(ret) => ret.value.clone(),
+                _ => line.clone(),
+            };
+            remap.visit_expr(&mut line);
+            lines.push(line);
+        }
+        self.sites += 1;
+        self.inlined.insert(name);
+        Some(lines)
+    }
+}
+impl MutVisitor for Inliner<'_> {
+    fn visit_expr(&mut self, expr: &mut Box<dyn Expression>) {
+        walk_expr_mut(self, expr);
+        if let Some(mut lines) = self.inline(expr.as_ref()) {
+            //functions ending in a statement return 0
+            if !lines.last().is_some_and(|line| has_value(line.as_ref())) {
+                lines.push(Box::new(Number(String::from("0"), Span::default())));
+            }
+            *expr = Box::new(CodeBlock::new(lines, BlockType::Brack, None));
+        }
+    }
+    //a call that is a line of its own gets spliced into the block, so its variables are in
+    //the block's scope like any others
+    fn visit_block(&mut self, block: &mut CodeBlock) {
+        if block.block_type == BlockType::Brack {
+            return walk_block_mut(self, block);
+        }
+        for mut line in std::mem::take(&mut block.lines) {
+            walk_expr_mut(self, &mut line);
+            match self.inline(line.as_ref()) {
+                Some(lines) => block.lines.extend(lines),
+                None => block.lines.push(line),
+            }
+        }
+    }
+    fn visit_func_decl(&mut self, decl: &mut FuncDecl) {
+        let outer = std::mem::replace(&mut self.frame, decl.frame.clone());
+        walk_func_decl_mut(self, decl);
+        self.frame = outer;
+    }
+}
+struct Remover {
+    unused: HashSet<String>,
+    removed: bool,
+}
+impl MutVisitor for Remover {
+    fn visit_block(&mut self, block: &mut CodeBlock) {
+        let before = block.lines.len();
+        block.lines.retain(|line| match line.node() {
+            Node::FuncDecl(decl) => !self.unused.contains(&decl.name),
+            _ => true,
+        });
+        self.removed |= block.lines.len() < before;
+        walk_block_mut(self, block);
+    }
+}
+struct Remap<'a> {
+    prefix: String,
+    //slot in the callee to slot in the caller
+    slots: HashMap<usize, usize>,
+    frame: &'a FrameStack,
+}
+impl Remap<'_> {
+    fn remap(&mut self, addr: &mut usize, name: &mut String) {
+        *addr = *self.slots.entry(*addr).or_insert_with(|| self.frame.new_slot());
+        *name = format!("{}{name}", self.prefix);
+    }
+}
+impl MutVisitor for Remap<'_> {
+    fn visit_expr(&mut self, expr: &mut Box<dyn Expression>) {
+        expr.set_span(Span::default());
+        walk_expr_mut(self, expr);
+    }
+    fn visit_var(&mut self, var: &mut Var) {
+        self.remap(&mut var.addr, &mut var.name);
+    }
+    fn visit_var_decl(&mut self, decl: &mut VarDecl) {
+        walk_var_decl_mut(self, decl);
+        self.remap(&mut decl.addr, &mut decl.name);
+    }
+}
+
+fn has_value(expr: &dyn Expression) -> bool {
+    match expr.node() {
+        Node::Number(_) | Node::Str(_) | Node::Bool(_) | Node::Var(_) | Node::BinOp(_) => true,
+        Node::Array(_) | Node::Index(_) | Node::StructLit(_) | Node::Field(_) => true,
+        Node::Call(call) => call.func.callee().returns(),
+        Node::Block(block) => block.lines.last().is_some_and(|line| has_value(line.as_ref())),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{front_end, lower_source, middle_end};
+
+    fn calls_in_main(s: &str) -> Vec<String> {
+        let module = lower_source(s).unwrap();
+        module
+            .main
+            .to_string()
+            .lines()
+            .filter(|line| line.split_whitespace().any(|word| word == "call"))
+            .map(|line| line.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn inlines_small_functions() {
+        let s = "def double(x) { x * 2 } def show(a) { print(a) } show(double(21))";
+        let module = lower_source(s).unwrap();
+        let expected = "func main():
+    double.0.x@0 = 21
+    t0 = double.0.x@0 << 1
+    show.1.a@1 = t0
+    syscall 1(show.1.a@1)
+    return 0
+";
+        assert_eq!(module.main.to_string(), expected);
+        //nothing calls them anymore
+        assert!(module.functions.is_empty());
+    }
+    #[test]
+    fn keeps_recursive_and_large_functions() {
+        let fac = "def fac(n) { if (n < 2) { return 1 } return n * fac(n - 1) } print(fac(5))";
+        assert_eq!(calls_in_main(fac), ["t0 = call fac(5)"]);
+        let big = "def big(a) { b sei a * a; c sei b * b + a; print(c); c * b + a - 1 } print(big(2))";
+        assert_eq!(calls_in_main(big), ["t0 = call big(2)"]);
+        assert!(calls_in_main(&format!("inline {big}")).is_empty());
+    }
+    #[test]
+    fn warns_about_inline_functions_it_cant_inline() {
+        let s = "inline def sign(n) { if (n < 0) { return 0 - 1 } 1 } print(sign(read_int()))";
+        let (ast, warnings) = front_end(s).unwrap();
+        let warnings = warnings.iter().map(|w| (w.message.as_str(), &s[w.span.start..w.span.end]));
+        let expected = "inline def sign(n) { if (n < 0) { return 0 - 1 } 1 }";
+        let expected = ("`sign` can't be inlined, it returns early", expected);
+        assert_eq!(warnings.collect::<Vec<_>>(), [expected]);
+        assert_eq!(middle_end(&ast, false).functions.len(), 1);
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::HashSet;
+
+use crate::{diagnostic::Diagnostic, expression::CodeBlock, parser::resolve_vars};
+
+use self::{
+    const_fold::fold_constants,
+    dead_code::{collect_reads, eliminate_dead_code},
+    inline::{inline_functions, remove_inlined, warn_not_inlined},
+};
+
+pub mod const_fold;
+pub mod dead_code;
+pub mod inline;
+
+//constant folding, dead code elimination and inlining until none of them finds anything,
+//then the variables get their slots again so the removed ones don't take space in the frame
+pub fn simplify(program: &mut CodeBlock) -> Vec<Diagnostic> {
+    let source_reads = collect_reads(program);
+    let mut warnings = vec![];
+    let mut inlined = HashSet::new();
+    loop {
+        let folded = fold_constants(program);
+        let eliminated = eliminate_dead_code(program, &source_reads, &mut warnings);
+        //after the other two, so the functions are cleaned up before they are copied
+        if !inline_functions(program, &mut inlined) && !folded && !eliminated {
+            break;
+        }
+    }
+    remove_inlined(program, &inlined);
+    warn_not_inlined(program, &mut warnings);
+    resolve_vars(program);
+    warnings
+}
@@ -1,11 +1,20 @@
+use std::rc::Rc;
+
 use crate::{
     expression::{
-        statements::{FrameLayer, FrameStack, Number, Var},
+        statements::{
+            AllocFn, ArrayLit, Bool, BufferFn, FieldAssign, FrameStack, FuncDecl, FunctionCall,
+            IfBlock, Import, Index, IndexAssign, LenFn, Number, PrintFn, PushFn, ReadCharFn,
+            ReadIntFn, ReadStringFn, Return, Str, StructDecl, StructLit, ToFloatFn, ToIntFn,
+            TwoSideOp, Var, VarDecl, WhileBlock,
+        },
+        visit::{walk_block_mut, walk_expr_mut, MutVisitor},
         BlockType, CodeBlock, Expression,
     },
+    ir::BinOp,
     lexer::{
-        lex,
-        token::{Token, TokenType},
+        lex, unescape_str,
+        token::{Span, Token, TokenType},
     },
     pattern::{ExprBuilder, TORE},
 };
@@ -20,8 +29,8 @@ fn find_matching_bracket<'a>(
             &tokens_or_expr[brack_start],
             TORE::Token(
                 Token {
-                    token_type:_,
-                    slice
+                    slice,
+                    ..
                 })
             if slice == &open
         ),
@@ -58,9 +67,11 @@ fn parse_braces<'a>(
                 let brack_end = find_matching_bracket(&tokens_or_expr, ("{", "}"), i);
                 let nodes = tokens_or_expr[i + 1..brack_end].to_vec();
                 //parsing the tokens into an expression
+                let span = tokens_or_expr[i].span().join(tokens_or_expr[brack_end].span());
                 let mut frame = frame.push();
                 let lines = parse_tokens(nodes.to_vec(), builders, &mut frame);
-                let block = Box::new(CodeBlock::new(lines, BlockType::Curl, Some(frame)));
+                let mut block = Box::new(CodeBlock::new(lines, BlockType::Curl, Some(frame)));
+                block.set_span(span);
                 //replacing the tokens with the expression, delete the brackets as well
                 tokens_or_expr.splice(i..=brack_end, vec![TORE::Expr(block)]);
             }
@@ -82,8 +93,10 @@ fn parse_brackets<'a>(
                 let brack_end = find_matching_bracket(&tokens_or_expr, ("(", ")"), i);
                 let nodes = tokens_or_expr[i + 1..brack_end].to_vec();
                 //parsing the tokens into an expression
+                let span = tokens_or_expr[i].span().join(tokens_or_expr[brack_end].span());
                 let lines = parse_tokens(nodes.to_vec(), builders, frame);
-                let block = Box::new(CodeBlock::new(lines, BlockType::Brack, None));
+                let mut block = Box::new(CodeBlock::new(lines, BlockType::Brack, None));
+                block.set_span(span);
                 //replacing the tokens with the expression, delete the brackets as well
                 tokens_or_expr.splice(i..=brack_end, vec![TORE::Expr(block)]);
             }
@@ -92,6 +105,54 @@ fn parse_brackets<'a>(
     }
     tokens_or_expr
 }
+//array literals, `a[i]` starts as one too and becomes an Index later
+fn parse_squares<'a>(
+    mut tokens_or_expr: Vec<TORE<'a>>,
+    builders: &Vec<ExprBuilder>,
+    frame: &mut FrameStack,
+) -> Vec<TORE<'a>> {
+    let mut i = 0;
+    while i < tokens_or_expr.len() {
+        if let TORE::Token(token) = tokens_or_expr[i].clone() {
+            if token.slice == "[" {
+                let brack_end = find_matching_bracket(&tokens_or_expr, ("[", "]"), i);
+                let nodes = tokens_or_expr[i + 1..brack_end].to_vec();
+                let span = tokens_or_expr[i].span().join(tokens_or_expr[brack_end].span());
+                //`[value; len]`, a `;` of an inner array doesn't count
+                let mut level = 0;
+                let semicolon = nodes.iter().position(|t| match t {
+                    TORE::Token(t) if t.slice == "[" => {
+                        level += 1;
+                        false
+                    }
+                    TORE::Token(t) if t.slice == "]" => {
+                        level -= 1;
+                        false
+                    }
+                    TORE::Token(t) => t.slice == ";" && level == 0,
+                    TORE::Expr(_) => false,
+                });
+                let mut array = match semicolon {
+                    Some(semicolon) => {
+                        let (value, len) = nodes.split_at(semicolon);
+                        let mut value = parse_tokens(value.to_vec(), builders, frame);
+                        let mut len = parse_tokens(len[1..].to_vec(), builders, frame);
+                        assert!(
+                            value.len() == 1 && len.len() == 1,
+                            "`[value; length]` takes one value and one length"
+                        );
+                        ArrayLit::new(vec![value.remove(0)], Some(len.remove(0)))
+                    }
+                    None => ArrayLit::new(parse_tokens(nodes, builders, frame), None),
+                };
+                array.set_span(span);
+                tokens_or_expr.splice(i..=brack_end, vec![TORE::Expr(Box::new(array))]);
+            }
+        }
+        i += 1;
+    }
+    tokens_or_expr
+}
 fn parse_tokens(
     mut tokens: Vec<TORE>,
     builders: &Vec<ExprBuilder>,
@@ -99,10 +160,10 @@ fn parse_tokens(
 ) -> Vec<Box<dyn Expression>> {
     tokens = parse_braces(tokens, builders, frame);
     tokens = parse_brackets(tokens, builders, frame);
+    tokens = parse_squares(tokens, builders, frame);
     for builder in builders {
         tokens = builder.parse_occurences(tokens, frame);
     }
-    println!("{:#?}", tokens);
     //make lines
     let lines = tokens
         .into_iter()
@@ -113,7 +174,8 @@ fn parse_tokens(
                 } else if t.token_type == TokenType::Word {
                     let name = t.slice.to_string();
                     let addr = frame.get_addr(&name);
-                    let var: Box<dyn Expression> = Box::new(Var::new(name, addr));
+                    let mut var: Box<dyn Expression> = Box::new(Var::new(name, addr));
+                    var.set_span(t.span());
                     Some(var)
                 } else {
                     panic!("unexpected token: {}", t.slice)
@@ -124,25 +186,204 @@ fn parse_tokens(
         .collect::<Vec<_>>();
     lines
 }
-pub fn parse_nums(tokens: Vec<TORE>) -> Vec<TORE> {
+//numbers, strings, true and false
+pub fn parse_literals(tokens: Vec<TORE>) -> Vec<TORE> {
     tokens
         .into_iter()
         .map(|t| match t {
-            TORE::Token(Token {
-                token_type: TokenType::Number,
-                slice,
-            }) => TORE::Expr(Box::new(Number(slice.to_string()))),
+            TORE::Token(ref token) => {
+                let span = token.span();
+                let literal: Box<dyn Expression> = match (&token.token_type, token.slice) {
+                    (TokenType::Number | TokenType::Char, slice) => {
+                        Box::new(Number(slice.to_string(), span))
+                    }
+                    (TokenType::Str, slice) => Box::new(Str(unescape_str(slice), span)),
+                    (TokenType::Word, "true") => Box::new(Bool(true, span)),
+                    (TokenType::Word, "false") => Box::new(Bool(false, span)),
+                    _ => return t,
+                };
+                TORE::Expr(literal)
+            }
             _ => t,
         })
         .collect()
 }
+//the order matters: earlier builders bind tighter. calls come before the operators
+//(n * f(n - 1)), but after everything else that is a word followed by brackets. indexing
+//and fields come right after the calls (f(x)[0].y), a name followed by a curly block that
+//is left after the declarations makes a struct
+pub fn default_builders() -> Vec<ExprBuilder> {
+    vec![
+        Import::get_builder(),
+        Import::get_short_builder(),
+        IfBlock::get_builder(),
+        WhileBlock::get_builder(),
+        FuncDecl::get_builder(true, true),
+        FuncDecl::get_builder(true, false),
+        FuncDecl::get_builder(false, true),
+        FuncDecl::get_builder(false, false),
+        StructDecl::get_builder(),
+        StructLit::get_builder(),
+        StructLit::get_builder_qualified(),
+        FunctionCall::get_builder(Rc::new(PrintFn)),
+        FunctionCall::get_builder(Rc::new(ToFloatFn)),
+        FunctionCall::get_builder(Rc::new(ToIntFn)),
+        FunctionCall::get_builder(Rc::new(AllocFn)),
+        FunctionCall::get_builder(Rc::new(PushFn)),
+        FunctionCall::get_builder(Rc::new(LenFn)),
+        FunctionCall::get_builder(Rc::new(ReadIntFn)),
+        FunctionCall::get_builder(Rc::new(ReadCharFn)),
+        FunctionCall::get_builder(Rc::new(ReadStringFn)),
+        FunctionCall::get_builder(Rc::new(BufferFn)),
+        FunctionCall::get_builder_qualified(),
+        FunctionCall::get_builder_var(),
+        Index::get_builder(),
+        TwoSideOp::get_group_builder(&[BinOp::Mul, BinOp::Div, BinOp::Rem]),
+        TwoSideOp::get_group_builder(&[BinOp::Add, BinOp::Sub]),
+        TwoSideOp::get_builder(BinOp::Lt),
+        //`x: int sei` before `x sei`, which would take the type name for the variable. what
+        //is left of `name: type` afterwards are the parameters of functions
+        IndexAssign::get_builder(),
+        FieldAssign::get_builder(),
+        VarDecl::get_builder(true),
+        VarDecl::get_builder(false),
+        Var::get_typed_builder(),
+        Return::get_builder(),
+    ]
+}
 pub fn parse(code: String, builders: &Vec<ExprBuilder>) -> CodeBlock {
     let tokens = lex(&code);
-    let tokens_or_expr = tokens.into_iter().map(|t| TORE::Token(t)).collect();
-    let tokens_or_expr = parse_nums(tokens_or_expr);
-    println!("tokens: {:#?}", tokens_or_expr);
+    let tokens_or_expr = tokens.into_iter().map(TORE::Token).collect();
+    let tokens_or_expr = parse_literals(tokens_or_expr);
     let mut frame = FrameStack::new();
     let lines = parse_tokens(tokens_or_expr, builders, &mut frame);
-    let code = CodeBlock::new(lines, BlockType::Curl, Some(frame));
-    code
+    let mut program = CodeBlock::new(lines, BlockType::Curl, None);
+    program.span = Span::new(0, code.len());
+    resolve_vars(&mut program);
+    program
+}
+//moves the spans of a program parsed on its own behind others, the type checker tells nodes
+//apart by their span
+pub fn shift_spans(program: &mut CodeBlock, by: usize) {
+    Shift(by).visit_block(program);
+    program.span = Span::new(program.span.start + by, program.span.end + by);
+}
+struct Shift(usize);
+impl MutVisitor for Shift {
+    fn visit_expr(&mut self, expr: &mut Box<dyn Expression>) {
+        let span = expr.span();
+        expr.set_span(Span::new(span.start + self.0, span.end + self.0));
+        walk_expr_mut(self, expr);
+    }
+}
+//inner blocks get parsed before the code around them, so the addresses handed
+//out while parsing don't follow the source order. this assigns the final ones:
+//a name refers to the declaration in the closest enclosing block that came
+//before it, every function gets its own frame
+pub fn resolve_vars(program: &mut CodeBlock) {
+    let mut resolver = Resolver {
+        frame: FrameStack::new(),
+    };
+    walk_block_mut(&mut resolver, program);
+    program.frame = Some(resolver.frame);
+}
+struct Resolver {
+    frame: FrameStack,
+}
+impl MutVisitor for Resolver {
+    fn visit_block(&mut self, block: &mut CodeBlock) {
+        if block.block_type == BlockType::Brack {
+            return walk_block_mut(self, block);
+        }
+        let outer = std::mem::take(&mut self.frame);
+        self.frame = outer.push();
+        walk_block_mut(self, block);
+        block.frame = Some(std::mem::replace(&mut self.frame, outer));
+    }
+    fn visit_var(&mut self, var: &mut Var) {
+        var.addr = self.frame.get_addr(&var.name);
+    }
+    fn visit_var_decl(&mut self, decl: &mut VarDecl) {
+        //the value is evaluated before the variable is set (x sei x + 1)
+        self.visit_expr(&mut decl.value);
+        decl.addr = self.frame.get_addr(&decl.name);
+    }
+    fn visit_func_decl(&mut self, decl: &mut FuncDecl) {
+        let outer = std::mem::take(&mut self.frame);
+        for arg in &mut decl.args {
+            arg.addr = self.frame.get_addr(&arg.name);
+        }
+        self.visit_expr(&mut decl.body);
+        decl.frame = std::mem::replace(&mut self.frame, outer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::{
+        visit::{walk_func_decl, walk_var_decl, Visitor},
+        Node,
+    };
+
+    //the first line with brackets around every operation
+    fn grouped(s: &str) -> String {
+        fn group(expr: &dyn Expression) -> String {
+            match expr.node() {
+                Node::BinOp(op) => {
+                    let (a, b) = (group(op.values.0.as_ref()), group(op.values.1.as_ref()));
+                    format!("({a} {} {b})", op.sign)
+                }
+                Node::Number(number) => number.0.clone(),
+                Node::Var(var) => var.name.clone(),
+                _ => panic!("only operations on numbers and names"),
+            }
+        }
+        group(parse(s.to_string(), &default_builders()).lines[0].as_ref())
+    }
+
+    #[test]
+    fn parses_operators_left_to_right() {
+        assert_eq!(grouped("10 - 3 - 2"), "((10 - 3) - 2)");
+        assert_eq!(grouped("a + b + c"), "((a + b) + c)");
+        assert_eq!(grouped("100 / 10 % 3 * 2"), "(((100 / 10) % 3) * 2)");
+        assert_eq!(grouped("a - b + c"), "((a - b) + c)");
+    }
+    #[test]
+    fn parses_precedence() {
+        assert_eq!(grouped("1 + 2 * 3 - 4 / 2"), "((1 + (2 * 3)) - (4 / 2))");
+        assert_eq!(grouped("a % 2 < b - 1"), "((a % 2) < (b - 1))");
+    }
+    //`name@addr` of every variable and parameter in source order
+    fn addrs(s: &str) -> Vec<String> {
+        struct Addrs(Vec<String>);
+        impl Visitor for Addrs {
+            fn visit_var(&mut self, var: &Var) {
+                self.0.push(format!("{}@{}", var.name, var.addr));
+            }
+            fn visit_var_decl(&mut self, decl: &VarDecl) {
+                walk_var_decl(self, decl);
+                self.0.push(format!("{}@{}", decl.name, decl.addr));
+            }
+            fn visit_func_decl(&mut self, decl: &FuncDecl) {
+                decl.args.iter().for_each(|arg| self.visit_var(arg));
+                walk_func_decl(self, decl);
+            }
+        }
+        let mut addrs = Addrs(vec![]);
+        addrs.visit_block(&parse(s.to_string(), &default_builders()));
+        addrs.0
+    }
+
+    #[test]
+    fn gives_functions_their_own_frame() {
+        let s = "def f(n) { b sei n; b } a sei 2; print(f(a))";
+        assert_eq!(addrs(s), ["n@0", "n@0", "b@1", "b@1", "a@0", "a@0"]);
+    }
+    #[test]
+    fn resolves_names_to_the_closest_declaration_before() {
+        //the `x` in the block is the outer one, its `y` is gone after it
+        let s = "x sei 1; if (x) { y sei x; x sei y } y sei 2; print(y)";
+        assert_eq!(addrs(s), ["x@0", "x@0", "x@0", "y@1", "y@1", "x@0", "y@2", "y@2"]);
+    }
 }
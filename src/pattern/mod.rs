@@ -1,18 +1,39 @@
 use crate::{
     expression::{
-        statements::{FrameLayer, FrameStack, Var},
-        BlockType, Expression,
+        statements::{FrameStack, Var},
+        BlockType, Expression, Node, NodeMut,
     },
-    lexer::token::{Token, TokenType},
+    lexer::token::{Span, Token, TokenType},
 };
 
+//words that can't name a variable or function
+pub const KEYWORDS: [&str; 11] = [
+    "def", "inline", "if", "while", "sei", "return", "true", "false", "struct", "import", "use",
+];
+fn is_name(t: &Token) -> bool {
+    t.token_type == TokenType::Word && !KEYWORDS.contains(&t.slice)
+}
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug)]
 pub enum TORE<'a> {
     Token(Token<'a>),
     Expr(Box<dyn Expression>),
 }
+impl TORE<'_> {
+    pub fn span(&self) -> Span {
+        match self {
+            TORE::Token(t) => t.span(),
+            TORE::Expr(e) => e.span(),
+        }
+    }
+}
 pub trait SimplePattern {
     fn match_tore<'a>(&self, t: &TORE<'a>) -> Option<Option<Box<dyn Expression>>>;
+    //called once the whole pattern matched, e.g. to look up variables in the frame
+    fn resolve(&self, expr: Box<dyn Expression>, _frame: &mut FrameStack) -> Box<dyn Expression> {
+        expr
+    }
 }
 //just consumes text
 pub struct TextPatt(pub String);
@@ -31,18 +52,101 @@ pub struct TextPattVar;
 impl SimplePattern for TextPattVar {
     fn match_tore<'a>(&self, t: &TORE<'a>) -> Option<Option<Box<dyn Expression>>> {
         if let TORE::Token(t) = t {
-            let var = Var::new(t.slice.to_string(), 0);
+            let mut var = Var::new(t.slice.to_string(), 0);
+            var.set_span(t.span());
             Some(Some(Box::new(var)))
         } else {
             None
         }
     }
 }
+//returns a name, keywords don't match
+pub struct NamePatt;
+impl SimplePattern for NamePatt {
+    fn match_tore<'a>(&self, t: &TORE<'a>) -> Option<Option<Box<dyn Expression>>> {
+        if let TORE::Token(t) = t {
+            if is_name(t) {
+                let mut var = Var::new(t.slice.to_string(), 0);
+                var.set_span(t.span());
+                return Some(Some(Box::new(var)));
+            }
+        }
+        None
+    }
+}
+//consumes one of several texts and returns which one it was, like TextPattVar
+pub struct OneOfPatt(pub Vec<String>);
+impl SimplePattern for OneOfPatt {
+    fn match_tore<'a>(&self, t: &TORE<'a>) -> Option<Option<Box<dyn Expression>>> {
+        if let TORE::Token(t) = t {
+            if self.0.iter().any(|s| s == t.slice) {
+                let mut var = Var::new(t.slice.to_string(), 0);
+                var.set_span(t.span());
+                return Some(Some(Box::new(var)));
+            }
+        }
+        None
+    }
+}
 pub struct BlockPatt(pub BlockType);
 impl SimplePattern for BlockPatt {
     fn match_tore<'a>(&self, t: &TORE<'a>) -> Option<Option<Box<dyn Expression>>> {
         if let TORE::Expr(e) = t {
-            if e.get_name() == self.0.get_name() {
+            if matches!(e.node(), Node::Block(block) if block.block_type == self.0) {
+                return Some(Some(e.clone()));
+            }
+        }
+        None
+    }
+}
+//`[i]` after an array, returns the index. or `.name` after a struct, returned with the dot
+//like TextPattVar does, no variable name starts with one
+pub struct SubscriptPatt;
+impl SimplePattern for SubscriptPatt {
+    fn match_tore<'a>(&self, t: &TORE<'a>) -> Option<Option<Box<dyn Expression>>> {
+        match t {
+            TORE::Expr(e) => {
+                if let Node::Array(array) = e.node() {
+                    if let ([index], None) = (&array.values[..], &array.len) {
+                        return Some(Some(index.clone()));
+                    }
+                }
+            }
+            TORE::Token(_) => return MemberPatt.match_tore(t),
+        }
+        None
+    }
+}
+//`.name`, returned with the dot
+pub struct MemberPatt;
+impl SimplePattern for MemberPatt {
+    fn match_tore<'a>(&self, t: &TORE<'a>) -> Option<Option<Box<dyn Expression>>> {
+        match t {
+            TORE::Token(token) if token.token_type == TokenType::Member => {
+                TextPattVar.match_tore(t)
+            }
+            _ => None,
+        }
+    }
+}
+//an indexed array, the target of `a[i] sei v`
+pub struct IndexPatt;
+impl SimplePattern for IndexPatt {
+    fn match_tore<'a>(&self, t: &TORE<'a>) -> Option<Option<Box<dyn Expression>>> {
+        if let TORE::Expr(e) = t {
+            if let Node::Index(_) = e.node() {
+                return Some(Some(e.clone()));
+            }
+        }
+        None
+    }
+}
+//a field of a struct, the target of `p.x sei v`
+pub struct FieldPatt;
+impl SimplePattern for FieldPatt {
+    fn match_tore<'a>(&self, t: &TORE<'a>) -> Option<Option<Box<dyn Expression>>> {
+        if let TORE::Expr(e) = t {
+            if let Node::Field(_) = e.node() {
                 return Some(Some(e.clone()));
             }
         }
@@ -55,14 +159,21 @@ impl SimplePattern for ExprPattern {
         if let TORE::Expr(e) = t {
             return Some(Some(e.clone()));
         } else if let TORE::Token(t) = t {
-            if t.token_type == TokenType::Word {
+            if is_name(t) {
                 let name = t.slice.to_string();
-                let var: Box<dyn Expression> = Box::new(Var::new(name, 0));
+                let mut var: Box<dyn Expression> = Box::new(Var::new(name, 0));
+                var.set_span(t.span());
                 return Some(Some(var));
             }
         }
         None
     }
+    fn resolve(&self, mut expr: Box<dyn Expression>, frame: &mut FrameStack) -> Box<dyn Expression> {
+        if let NodeMut::Var(var) = expr.node_mut() {
+            var.addr = frame.get_addr(&var.name);
+        }
+        expr
+    }
 }
 pub type ExprConstr = Box<dyn Fn(Vec<Box<dyn Expression>>, &mut FrameStack) -> Box<dyn Expression>>;
 pub struct ExprBuilder {
@@ -83,21 +194,28 @@ impl ExprBuilder {
     ) -> Vec<TORE<'a>> {
         let mut i = 0;
         'token_loop: while i + self.patterns.len() <= tokens.len() {
-            let mut params = vec![];
+            let mut matches = vec![];
             for (off, pattern) in self.patterns.iter().enumerate() {
                 let token = &tokens[i + off];
                 if let Some(result) = pattern.match_tore(token) {
                     if let Some(result) = result {
-                        params.push(result);
+                        matches.push((pattern, result));
                     }
                 } else {
                     i += 1;
                     continue 'token_loop;
                 }
             }
-            let expr = TORE::Expr((self.constructor)(params, frame));
+            let params = matches
+                .into_iter()
+                .map(|(pattern, result)| pattern.resolve(result, frame))
+                .collect();
+            let span = tokens[i].span().join(tokens[i + self.patterns.len() - 1].span());
+            let mut expr = (self.constructor)(params, frame);
+            expr.set_span(span);
+            let expr = TORE::Expr(expr);
+            //stay at i, the new expression can start the next match (a + b + c)
             tokens.splice(i..(i + self.patterns.len()), [expr]);
-            i += 1;
         }
         tokens
     }
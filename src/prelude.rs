@@ -0,0 +1,93 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    expression::{
+        statements::{FuncDecl, FunctionCall},
+        visit::{walk_call, walk_func_decl, Visitor},
+        CodeBlock, Node,
+    },
+    ir::Callee,
+    parser::{default_builders, parse, shift_spans},
+};
+
+//functions every program can call without declaring them, written in the language itself
+const SOURCES: [&str; 2] = [include_str!("prelude/math.src"), include_str!("prelude/io.src")];
+
+//adds the prelude functions the program calls, directly or through other prelude functions,
+//to its end. the others are left out so they don't end up in every program. a function the
+//program declares itself wins over the one of the prelude with the same name
+pub fn link(program: &mut CodeBlock) {
+    let mut names = NameCollector::default();
+    names.visit_block(program);
+    //the rewriter of the type checker tells nodes apart by their span, so the prelude gets
+    //the spans after the end of the program
+    let mut start = program.span.end + 1;
+    let mut funcs = HashMap::new();
+    let mut order = vec![];
+    for source in SOURCES {
+        let mut prelude = parse(source.to_string(), &default_builders());
+        shift_spans(&mut prelude, start);
+        start += source.len() + 1;
+        for line in prelude.lines {
+            if let Node::FuncDecl(decl) = line.node() {
+                order.push(decl.name.clone());
+                funcs.insert(decl.name.clone(), line);
+            }
+        }
+    }
+    let mut todo: Vec<String> = names.calls.difference(&names.decls).cloned().collect();
+    let mut linked = HashSet::new();
+    while let Some(name) = todo.pop() {
+        let Some(line) = funcs.get(&name) else {
+            continue;
+        };
+        if !linked.insert(name) {
+            continue;
+        }
+        let mut calls = NameCollector::default();
+        calls.visit_expr(line.as_ref());
+        todo.extend(calls.calls.into_iter().filter(|call| !names.decls.contains(call)));
+    }
+    for name in order.into_iter().filter(|name| linked.contains(name)) {
+        program.lines.extend(funcs.remove(&name));
+    }
+}
+
+#[derive(Default)]
+struct NameCollector {
+    calls: HashSet<String>,
+    decls: HashSet<String>,
+}
+impl Visitor for NameCollector {
+    fn visit_call(&mut self, call: &FunctionCall) {
+        if let Callee::Func(name) = call.func.callee() {
+            self.calls.insert(name);
+        }
+        walk_call(self, call);
+    }
+    fn visit_func_decl(&mut self, decl: &FuncDecl) {
+        self.decls.insert(decl.name.clone());
+        walk_func_decl(self, decl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile, lower_source, mips::sim::run};
+
+    fn functions(s: &str) -> Vec<String> {
+        let module = lower_source(s).unwrap();
+        let mut names: Vec<_> = module.functions.iter().map(|f| f.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn links_only_what_is_called() {
+        assert!(functions("print(1)").is_empty());
+        assert_eq!(functions("print(gcd(4, 6))"), ["abs", "gcd"]);
+        //the own `abs` gets inlined, the one of the prelude would be declared twice
+        assert!(functions("def abs(x) { 7 } print(abs(1))").is_empty());
+        assert_eq!(run(&compile("def abs(x) { 7 } print(abs(1))", false).unwrap(), ""), "7");
+    }
+}
@@ -0,0 +1,900 @@
+use std::{collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    diagnostic::Diagnostic,
+    expression::{
+        statements::{
+            Field, FieldAssign, FuncDecl, Function, FunctionCall, PrintFloatFn, PrintStrFn,
+            StructDecl, TwoSideOp,
+        },
+        visit::{
+            walk_bin_op_mut, walk_call_mut, walk_expr, walk_field_assign_mut, walk_field_mut,
+            MutVisitor, Visitor,
+        },
+        CodeBlock, Expression, Node,
+    },
+    ir::{runtime::Runtime, BinOp, Callee},
+    lexer::{parse_int, token::Span},
+    opt::const_fold::const_value,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Bool,
+    Str,
+    Float,
+    //what `buffer(n)` makes and `read_string` reads into, prints like a string
+    Buf,
+    Array(Box<Type>),
+    Vec(Box<Type>),
+    //by name, they live on the heap and get passed by reference
+    Struct(String),
+}
+impl Type {
+    //the ones an annotation can name
+    pub const ALL: [Type; 4] = [Type::Int, Type::Bool, Type::Str, Type::Float];
+    //the type an annotation names
+    pub fn from_name(name: &str) -> Option<Type> {
+        Self::ALL.into_iter().find(|ty| ty.to_string() == name)
+    }
+}
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+            Type::Str => write!(f, "string"),
+            Type::Float => write!(f, "float"),
+            Type::Buf => write!(f, "buffer"),
+            Type::Array(elem) => write!(f, "[{elem}]"),
+            Type::Vec(elem) => write!(f, "vec[{elem}]"),
+            Type::Struct(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+//infers the types of all variables and functions, returns the errors. what isn't annotated
+//gets its type from the values assigned to it and the way it is used. prints of strings and
+//floats get their own syscall, operators on floats become the float operators
+pub fn check(program: &mut CodeBlock) -> Vec<Diagnostic> {
+    let mut checker = Checker::default();
+    let mut decls = SignatureCollector(&mut checker);
+    decls.visit_block(program);
+    checker.infer(program);
+    let mut rewriter = Rewriter::default();
+    for (span, var) in std::mem::take(&mut checker.prints) {
+        let func: Rc<dyn Function> = match checker.vars.resolve(var) {
+            Type::Str | Type::Buf => Rc::new(PrintStrFn),
+            Type::Float => Rc::new(PrintFloatFn),
+            Type::Int | Type::Bool => continue,
+            ty @ (Type::Array(_) | Type::Vec(_) | Type::Struct(_)) => {
+                checker.error(format!("`print` can't print {ty}"), span);
+                continue;
+            }
+        };
+        rewriter.calls.push((span, func));
+    }
+    //the operand types are only known now
+    for (span, op, var) in std::mem::take(&mut checker.ops) {
+        match (checker.vars.resolve(var), op.on_floats()) {
+            (Type::Int, _) => {}
+            (Type::Float, Some(on_floats)) => rewriter.ops.push((span, on_floats)),
+            (ty, _) => {
+                let message = format!("`{}` can't be used on {ty}", op.sign());
+                checker.error(message, span);
+            }
+        }
+    }
+    //arrays live in the frame of the function that made them, vectors on the heap
+    let funcs = checker.funcs.iter().map(|(name, sig)| (sig.span, name.clone(), sig.ret));
+    let mut funcs = funcs.collect::<Vec<_>>();
+    funcs.sort_by_key(|(span, ..)| span.start);
+    for (span, name, ret) in funcs {
+        if let ty @ Type::Array(_) = checker.vars.resolve(ret) {
+            let message = format!("`{name}` can't return {ty}, arrays don't outlive the call");
+            checker.error(message, span);
+        }
+    }
+    rewriter.fields = std::mem::take(&mut checker.fields);
+    rewriter.visit_block(program);
+    checker.errors
+}
+
+//type variables, equal ones get merged (union find)
+#[derive(Default)]
+struct TypeVars {
+    parent: Vec<usize>,
+    terms: Vec<Option<Term>>,
+    //names of the structs, Term::Struct indexes into them
+    structs: Vec<String>,
+}
+type TypeVar = usize;
+//what is known about a type variable, the elements of an array are type variables themselves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Term {
+    Int,
+    Bool,
+    Str,
+    Float,
+    Buf,
+    Array(TypeVar),
+    Vec(TypeVar),
+    //an array or a vector, what indexing and `len` need. becomes whichever it meets
+    Seq(TypeVar),
+    Struct(usize),
+}
+impl Term {
+    fn elem(self) -> Option<TypeVar> {
+        match self {
+            Term::Array(elem) | Term::Vec(elem) | Term::Seq(elem) => Some(elem),
+            _ => None,
+        }
+    }
+    fn fits(self, other: Term) -> bool {
+        matches!(
+            (self, other),
+            (Term::Array(_), Term::Array(_))
+                | (Term::Vec(_), Term::Vec(_))
+                | (Term::Seq(_), _)
+                | (_, Term::Seq(_))
+        )
+    }
+}
+impl TypeVars {
+    fn fresh(&mut self) -> TypeVar {
+        self.parent.push(self.parent.len());
+        self.terms.push(None);
+        self.parent.len() - 1
+    }
+    fn term(&mut self, term: Term) -> TypeVar {
+        let var = self.fresh();
+        self.terms[var] = Some(term);
+        var
+    }
+    fn known(&mut self, ty: Type) -> TypeVar {
+        let term = match ty {
+            Type::Int => Term::Int,
+            Type::Bool => Term::Bool,
+            Type::Str => Term::Str,
+            Type::Float => Term::Float,
+            Type::Buf => Term::Buf,
+            Type::Array(elem) => Term::Array(self.known(*elem)),
+            Type::Vec(elem) => Term::Vec(self.known(*elem)),
+            Type::Struct(name) => {
+                let id = self.structs.iter().position(|s| *s == name);
+                Term::Struct(id.unwrap_or_else(|| panic!("unknown struct {name}")))
+            }
+        };
+        self.term(term)
+    }
+    fn find(&mut self, var: TypeVar) -> TypeVar {
+        if self.parent[var] != var {
+            self.parent[var] = self.find(self.parent[var]);
+        }
+        self.parent[var]
+    }
+    //whether `var` is part of the type of `of`, `a sei [a]` would make an endless type
+    fn occurs(&mut self, var: TypeVar, of: TypeVar) -> bool {
+        let of = self.find(of);
+        match self.terms[of] {
+            _ if of == var => true,
+            Some(term) => term.elem().is_some_and(|elem| self.occurs(var, elem)),
+            None => false,
+        }
+    }
+    //on a mismatch the two types, the expected one first
+    fn unify(&mut self, expected: TypeVar, found: TypeVar) -> Result<(), (Type, Type)> {
+        let (a, b) = (self.find(expected), self.find(found));
+        if a == b {
+            return Ok(());
+        }
+        let (term_a, term_b) = (self.terms[a], self.terms[b]);
+        let mismatch = match (term_a, term_b) {
+            (Some(x), Some(y)) => match (x.elem(), y.elem()) {
+                (Some(x_elem), Some(y_elem)) if x.fits(y) => self.unify(x_elem, y_elem).is_err(),
+                _ => x != y,
+            },
+            (None, Some(_)) => self.occurs(a, b),
+            (Some(_), None) => self.occurs(b, a),
+            (None, None) => false,
+        };
+        if mismatch {
+            return Err((self.resolve(a), self.resolve(b)));
+        }
+        self.terms[a] = match (term_a, term_b) {
+            (None | Some(Term::Seq(_)), Some(term)) => Some(term),
+            (term, _) => term,
+        };
+        self.parent[b] = a;
+        Ok(())
+    }
+    //what nothing says anything about is an int
+    fn resolve(&mut self, var: TypeVar) -> Type {
+        let root = self.find(var);
+        match self.terms[root] {
+            None | Some(Term::Int) => Type::Int,
+            Some(Term::Bool) => Type::Bool,
+            Some(Term::Str) => Type::Str,
+            Some(Term::Float) => Type::Float,
+            Some(Term::Buf) => Type::Buf,
+            Some(Term::Array(elem) | Term::Seq(elem)) => Type::Array(Box::new(self.resolve(elem))),
+            Some(Term::Vec(elem)) => Type::Vec(Box::new(self.resolve(elem))),
+            Some(Term::Struct(id)) => Type::Struct(self.structs[id].clone()),
+        }
+    }
+}
+
+struct Signature {
+    params: Vec<TypeVar>,
+    ret: TypeVar,
+    span: Span,
+}
+//the types of the fields of a struct, in the order of the words they are in
+struct Layout {
+    fields: Vec<(String, TypeVar)>,
+}
+
+#[derive(Default)]
+struct Checker {
+    vars: TypeVars,
+    funcs: HashMap<String, Signature>,
+    //by the id of Term::Struct
+    structs: Vec<Layout>,
+    //variables by function and address
+    locals: HashMap<(Option<String>, usize), TypeVar>,
+    //the function the checked code is in, None for the top level
+    func: Option<String>,
+    //argument of every print call by the span of the call
+    prints: Vec<(Span, TypeVar)>,
+    //operand of every operator, both have the same type
+    ops: Vec<(Span, BinOp, TypeVar)>,
+    //slot of every field access and assignment
+    fields: Vec<(Span, usize)>,
+    errors: Vec<Diagnostic>,
+}
+impl Checker {
+    fn local(&mut self, addr: usize) -> TypeVar {
+        let key = (self.func.clone(), addr);
+        match self.locals.get(&key) {
+            Some(var) => *var,
+            None => {
+                let var = self.vars.fresh();
+                self.locals.insert(key, var);
+                var
+            }
+        }
+    }
+    fn error(&mut self, message: String, span: Span) {
+        self.errors.push(Diagnostic::error(message, span));
+    }
+    //the value of an expression that has to have one
+    fn value(&mut self, expr: &dyn Expression) -> TypeVar {
+        match self.infer(expr) {
+            Some(var) => var,
+            None => {
+                self.error(format!("{} has no value", expr.get_name()), expr.span());
+                self.vars.fresh()
+            }
+        }
+    }
+    fn expect(&mut self, expr: &dyn Expression, ty: Type, what: &str) {
+        let var = self.value(expr);
+        let expected = self.vars.known(ty);
+        if let Err((ty, found)) = self.vars.unify(expected, var) {
+            self.error(format!("{what} must be {ty}, found {found}"), expr.span());
+        }
+    }
+    //None for statements
+    fn infer(&mut self, expr: &dyn Expression) -> Option<TypeVar> {
+        match expr.node() {
+            Node::Block(block) => {
+                let mut value = None;
+                for line in &block.lines {
+                    value = self.infer(line.as_ref());
+                }
+                value
+            }
+            Node::Number(number) if number.is_float() => {
+                if number.0.parse::<f32>().is_err() {
+                    self.error(format!("`{}` is not a number", number.0), number.1);
+                }
+                Some(self.vars.known(Type::Float))
+            }
+            Node::Number(number) => {
+                if let Err(message) = parse_int(&number.0) {
+                    self.error(message, number.1);
+                }
+                Some(self.vars.known(Type::Int))
+            }
+            Node::Str(_) => Some(self.vars.known(Type::Str)),
+            Node::Bool(_) => Some(self.vars.known(Type::Bool)),
+            Node::Var(var) => {
+                if let Some(ty) = &var.ty {
+                    self.annotate(&var.name, var.addr, ty.clone(), var.span);
+                }
+                //a declaration or parameter before it made the local, the resolver gives a
+                //name that has none a slot of its own
+                if !self.locals.contains_key(&(self.func.clone(), var.addr)) {
+                    self.error(format!("there is no variable `{}`", var.name), var.span);
+                }
+                Some(self.local(var.addr))
+            }
+            Node::BinOp(op) => {
+                let lhs = self.value(op.values.0.as_ref());
+                let rhs = self.value(op.values.1.as_ref());
+                if let Err((a, b)) = self.vars.unify(lhs, rhs) {
+                    self.error(format!("`{}` can't combine {a} and {b}", op.sign), op.span);
+                }
+                self.ops.push((op.span, op.op, lhs));
+                match op.op {
+                    BinOp::Lt => Some(self.vars.known(Type::Bool)),
+                    _ => Some(lhs),
+                }
+            }
+            Node::VarDecl(decl) => {
+                //the value comes first, `x sei x + 1` doesn't declare the x it reads
+                let value = self.value(decl.value.as_ref());
+                if let Some(ty) = &decl.ty {
+                    self.annotate(&decl.name, decl.addr, ty.clone(), decl.span);
+                }
+                let var = self.local(decl.addr);
+                if let Err((ty, found)) = self.vars.unify(var, value) {
+                    let message = format!("`{}` is {ty}, can't assign {found}", decl.name);
+                    self.error(message, decl.value.span());
+                }
+                None
+            }
+            Node::If(if_block) => {
+                self.expect(if_block.cond.as_ref(), Type::Bool, "condition");
+                self.infer(if_block.code.as_ref());
+                None
+            }
+            Node::While(while_block) => {
+                self.expect(while_block.cond.as_ref(), Type::Bool, "condition");
+                self.infer(while_block.code.as_ref());
+                None
+            }
+            Node::Call(call) => self.infer_call(call),
+            Node::Array(array) => {
+                let elem = self.vars.fresh();
+                for value in &array.values {
+                    let value_var = self.value(value.as_ref());
+                    if let Err((ty, found)) = self.vars.unify(elem, value_var) {
+                        let message = format!("array elements must all be {ty}, found {found}");
+                        self.error(message, value.span());
+                    }
+                }
+                if let Some(len) = &array.len {
+                    if const_value(len.as_ref()).is_none_or(|len| len < 0) {
+                        let message = String::from("array length must be a constant of at least 0");
+                        self.error(message, len.span());
+                    }
+                }
+                Some(self.vars.term(Term::Array(elem)))
+            }
+            Node::Index(index) => Some(self.element(index.array.as_ref(), index.index.as_ref())),
+            Node::IndexAssign(assign) => {
+                let elem = self.element(assign.array.as_ref(), assign.index.as_ref());
+                let value = self.value(assign.value.as_ref());
+                if let Err((ty, found)) = self.vars.unify(elem, value) {
+                    let message = format!("elements are {ty}, can't assign {found}");
+                    self.error(message, assign.value.span());
+                }
+                None
+            }
+            Node::StructDecl(_) | Node::Import(_) => None,
+            Node::StructLit(lit) => {
+                let values = lit.values.iter().map(|value| self.value(value.as_ref()));
+                let values = values.collect::<Vec<_>>();
+                let Some(id) = self.vars.structs.iter().position(|name| *name == lit.name) else {
+                    self.error(format!("there is no struct `{}`", lit.name), lit.span);
+                    return Some(self.vars.fresh());
+                };
+                let fields = self.structs[id].fields.clone();
+                if fields.len() != values.len() {
+                    let (n, s) = (fields.len(), if fields.len() == 1 { "" } else { "s" });
+                    let name = &lit.name;
+                    let message = format!("`{name}` has {n} field{s}, found {}", values.len());
+                    self.error(message, lit.span);
+                }
+                let name = &lit.name;
+                for (i, ((field, var), value)) in fields.into_iter().zip(values).enumerate() {
+                    if let Err((ty, found)) = self.vars.unify(var, value) {
+                        let message =
+                            format!("field `{field}` of `{name}` must be {ty}, found {found}");
+                        self.error(message, lit.values[i].span());
+                    }
+                }
+                Some(self.vars.term(Term::Struct(id)))
+            }
+            Node::Field(field) => Some(self.field(field.object.as_ref(), &field.name, field.span)),
+            Node::FieldAssign(assign) => {
+                let var = self.field(assign.object.as_ref(), &assign.name, assign.span);
+                let value = self.value(assign.value.as_ref());
+                if let Err((ty, found)) = self.vars.unify(var, value) {
+                    let message = format!("field `{}` is {ty}, can't assign {found}", assign.name);
+                    self.error(message, assign.value.span());
+                }
+                None
+            }
+            Node::Return(ret) => {
+                let value = self.value(ret.value.as_ref());
+                self.returns(value, ret.value.span());
+                None
+            }
+            Node::FuncDecl(decl) => {
+                let outer = self.func.replace(decl.name.clone());
+                let value = self.infer(decl.body.as_ref());
+                let last = last_line(decl);
+                //without a value at the end it returns 0
+                let value = match value {
+                    Some(value) => Some(value),
+                    None if matches!(last.node(), Node::Return(_)) => None,
+                    None => Some(self.vars.known(Type::Int)),
+                };
+                if let Some(value) = value {
+                    self.returns(value, last.span());
+                }
+                self.func = outer;
+                None
+            }
+        }
+    }
+    fn infer_call(&mut self, call: &FunctionCall) -> Option<TypeVar> {
+        let args = call.args.iter().map(|arg| self.value(arg.as_ref())).collect::<Vec<_>>();
+        match call.func.callee() {
+            //print takes anything, which syscall it becomes is decided at the end
+            Callee::Syscall { code: 1, .. } => {
+                if let [arg] = args[..] {
+                    self.prints.push((call.span, arg));
+                }
+                let params = [self.vars.fresh()];
+                let ret = self.vars.fresh();
+                self.check_args(call, "print", &params, args, ret);
+                None
+            }
+            //reading input, read_string fills the buffer it gets
+            Callee::Syscall { code: 5 | 12, .. } => {
+                let ret = self.vars.known(Type::Int);
+                Some(self.check_args(call, &call.func.get_name(), &[], args, ret))
+            }
+            Callee::Syscall { code: 8, .. } => {
+                let params = [self.vars.known(Type::Buf), self.vars.known(Type::Int)];
+                let ret = self.vars.fresh();
+                self.check_args(call, "read_string", &params, args, ret);
+                None
+            }
+            Callee::Syscall { returns, .. } => returns.then(|| self.vars.known(Type::Int)),
+            Callee::ToFloat => {
+                let params = vec![self.vars.known(Type::Int)];
+                let ret = self.vars.known(Type::Float);
+                Some(self.check_args(call, "float", &params, args, ret))
+            }
+            Callee::ToInt => {
+                let params = vec![self.vars.known(Type::Float)];
+                let ret = self.vars.known(Type::Int);
+                Some(self.check_args(call, "int", &params, args, ret))
+            }
+            Callee::Runtime(Runtime::Alloc) => {
+                let params = vec![self.vars.known(Type::Int)];
+                let elem = self.vars.fresh();
+                let ret = self.vars.term(Term::Vec(elem));
+                Some(self.check_args(call, "alloc", &params, args, ret))
+            }
+            Callee::Runtime(Runtime::Push) => {
+                let elem = self.vars.fresh();
+                let params = vec![self.vars.term(Term::Vec(elem)), elem];
+                self.check_args(call, "push", &params, args, elem);
+                None
+            }
+            Callee::Runtime(Runtime::Buffer) => {
+                let params = vec![self.vars.known(Type::Int)];
+                let ret = self.vars.known(Type::Buf);
+                Some(self.check_args(call, "buffer", &params, args, ret))
+            }
+            Callee::Len => {
+                let elem = self.vars.fresh();
+                let params = vec![self.vars.term(Term::Seq(elem))];
+                let ret = self.vars.known(Type::Int);
+                Some(self.check_args(call, "len", &params, args, ret))
+            }
+            Callee::Func(name) => {
+                //the signatures are collected first, a function can be called before it is
+                //declared
+                let Some(sig) = self.funcs.get(&name) else {
+                    self.error(format!("there is no function `{name}`"), call.span);
+                    return Some(self.vars.fresh());
+                };
+                let (params, ret) = (sig.params.clone(), sig.ret);
+                Some(self.check_args(call, &name, &params, args, ret))
+            }
+        }
+    }
+    //type of the elements of `array`, which may be a vector as well
+    fn element(&mut self, array: &dyn Expression, index: &dyn Expression) -> TypeVar {
+        let found = self.value(array);
+        let elem = self.vars.fresh();
+        let expected = self.vars.term(Term::Seq(elem));
+        if let Err((_, found)) = self.vars.unify(expected, found) {
+            let message = format!("only arrays and vectors can be indexed, found {found}");
+            self.error(message, array.span());
+        }
+        self.expect(index, Type::Int, "index");
+        elem
+    }
+    //type of the field `name` of `object`. the struct comes from the type of the object, while
+    //that isn't known yet from the field name if only one struct has it
+    fn field(&mut self, object: &dyn Expression, name: &str, span: Span) -> TypeVar {
+        let found = self.value(object);
+        let root = self.vars.find(found);
+        let id = match self.vars.terms[root] {
+            Some(Term::Struct(id)) => id,
+            None => {
+                let has_field = |layout: &Layout| layout.fields.iter().any(|(f, _)| f == name);
+                let mut owners = (0..self.structs.len()).filter(|&id| has_field(&self.structs[id]));
+                match (owners.next(), owners.next()) {
+                    (Some(id), None) => {
+                        let expected = self.vars.term(Term::Struct(id));
+                        let _ = self.vars.unify(expected, found);
+                        id
+                    }
+                    (Some(_), Some(_)) => {
+                        let message =
+                            format!("several structs have a field `{name}`, which one is unclear");
+                        self.error(message, span);
+                        return self.vars.fresh();
+                    }
+                    (None, _) => {
+                        self.error(format!("no struct has a field `{name}`"), span);
+                        return self.vars.fresh();
+                    }
+                }
+            }
+            Some(_) => {
+                let ty = self.vars.resolve(found);
+                self.error(format!("{ty} has no fields"), object.span());
+                return self.vars.fresh();
+            }
+        };
+        match self.structs[id].fields.iter().position(|(field, _)| field == name) {
+            Some(slot) => {
+                self.fields.push((span, slot));
+                self.structs[id].fields[slot].1
+            }
+            None => {
+                let message = format!("`{}` has no field `{name}`", self.vars.structs[id]);
+                self.error(message, span);
+                self.vars.fresh()
+            }
+        }
+    }
+    fn check_args(
+        &mut self,
+        call: &FunctionCall,
+        name: &str,
+        params: &[TypeVar],
+        args: Vec<TypeVar>,
+        ret: TypeVar,
+    ) -> TypeVar {
+        if params.len() != args.len() {
+            let (n, s) = (params.len(), if params.len() == 1 { "" } else { "s" });
+            let message = format!("`{name}` takes {n} argument{s}, found {}", args.len());
+            self.error(message, call.span);
+        }
+        for (i, (param, arg)) in params.iter().zip(args).enumerate() {
+            if let Err((ty, found)) = self.vars.unify(*param, arg) {
+                let message = format!("argument {} of `{name}` must be {ty}, found {found}", i + 1);
+                self.error(message, call.args[i].span());
+            }
+        }
+        ret
+    }
+    fn annotate(&mut self, name: &str, addr: usize, ty: Type, span: Span) {
+        let var = self.local(addr);
+        let annotation = self.vars.known(ty);
+        if let Err((ty, found)) = self.vars.unify(var, annotation) {
+            self.error(format!("`{name}` is {ty}, can't be {found}"), span);
+        }
+    }
+    fn returns(&mut self, value: TypeVar, span: Span) {
+        //what the top level returns is thrown away
+        let Some(name) = self.func.clone() else {
+            return;
+        };
+        let ret = self.funcs[&name].ret;
+        if let Err((ty, found)) = self.vars.unify(ret, value) {
+            self.error(format!("`{name}` returns {ty}, found {found}"), span);
+        }
+    }
+}
+//where the value of a function body comes from
+fn last_line(decl: &FuncDecl) -> &dyn Expression {
+    match decl.body.node() {
+        Node::Block(block) => block.lines.last().map_or(decl.body.as_ref(), |line| line.as_ref()),
+        _ => decl.body.as_ref(),
+    }
+}
+
+//functions can be called before they are declared, so the signatures come first
+struct SignatureCollector<'a>(&'a mut Checker);
+impl Visitor for SignatureCollector<'_> {
+    fn visit_func_decl(&mut self, decl: &FuncDecl) {
+        let checker = &mut *self.0;
+        let params = decl
+            .args
+            .iter()
+            .map(|arg| {
+                let var = match &arg.ty {
+                    Some(ty) => checker.vars.known(ty.clone()),
+                    None => checker.vars.fresh(),
+                };
+                checker.locals.insert((Some(decl.name.clone()), arg.addr), var);
+                var
+            })
+            .collect();
+        let ret = match &decl.ret {
+            Some(ty) => checker.vars.known(ty.clone()),
+            None => checker.vars.fresh(),
+        };
+        let span = decl.span;
+        if checker.funcs.contains_key(&decl.name) {
+            checker.error(format!("`{}` is declared twice", decl.name), span);
+        } else {
+            checker.funcs.insert(decl.name.clone(), Signature { params, ret, span });
+        }
+        walk_expr(self, decl.body.as_ref());
+    }
+    fn visit_struct_decl(&mut self, decl: &StructDecl) {
+        let checker = &mut *self.0;
+        if checker.vars.structs.contains(&decl.name) {
+            checker.error(format!("`{}` is declared twice", decl.name), decl.span);
+            return;
+        }
+        let fields = decl
+            .fields
+            .iter()
+            .map(|field| {
+                let var = match &field.ty {
+                    Some(ty) => checker.vars.known(ty.clone()),
+                    None => checker.vars.fresh(),
+                };
+                (field.name.clone(), var)
+            })
+            .collect();
+        checker.vars.structs.push(decl.name.clone());
+        checker.structs.push(Layout { fields });
+    }
+}
+
+//puts in what the types decided, found by the span of the node
+#[derive(Default)]
+struct Rewriter {
+    calls: Vec<(Span, Rc<dyn Function>)>,
+    ops: Vec<(Span, BinOp)>,
+    fields: Vec<(Span, usize)>,
+}
+impl MutVisitor for Rewriter {
+    fn visit_call(&mut self, call: &mut FunctionCall) {
+        if let Some((_, func)) = self.calls.iter().find(|(span, _)| *span == call.span) {
+            call.func = func.clone();
+        }
+        walk_call_mut(self, call);
+    }
+    fn visit_bin_op(&mut self, op: &mut TwoSideOp) {
+        if let Some((_, on_floats)) = self.ops.iter().find(|(span, _)| *span == op.span) {
+            op.op = *on_floats;
+        }
+        walk_bin_op_mut(self, op);
+    }
+    fn visit_field(&mut self, field: &mut Field) {
+        if let Some((_, slot)) = self.fields.iter().find(|(span, _)| *span == field.span) {
+            field.slot = *slot;
+        }
+        walk_field_mut(self, field);
+    }
+    fn visit_field_assign(&mut self, assign: &mut FieldAssign) {
+        if let Some((_, slot)) = self.fields.iter().find(|(span, _)| *span == assign.span) {
+            assign.slot = *slot;
+        }
+        walk_field_assign_mut(self, assign);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{default_builders, parse};
+
+    fn errors(s: &str) -> Vec<(String, &str)> {
+        let mut program = parse(s.to_string(), &default_builders());
+        check(&mut program).into_iter().map(|e| (e.message, &s[e.span.start..e.span.end])).collect()
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        assert_eq!(
+            errors("if (5 + \"a\") { print(1) }"),
+            [
+                (String::from("`+` can't combine int and string"), "5 + \"a\""),
+                (String::from("condition must be bool, found int"), "(5 + \"a\")"),
+            ]
+        );
+        let s = "def f(a) { a + 1 } x sei true; x sei f(\"b\")";
+        assert_eq!(
+            errors(s),
+            [
+                (String::from("argument 1 of `f` must be int, found string"), "\"b\""),
+                (String::from("`x` is bool, can't assign int"), "f(\"b\")"),
+            ]
+        );
+        assert!(
+            errors("def f(s) { print(s) } f(\"hi\"); b sei 1 < 2; if (b) { f(\"x\") }").is_empty()
+        );
+        let expected = [(String::from("`3000000000` doesn't fit into 32 bits"), "3000000000")];
+        assert_eq!(errors("print(3000000000)"), expected);
+        assert_eq!(
+            errors("read_string(\"..\", 2); x sei read_int(1); print(x + read_char())"),
+            [
+                (
+                    String::from("argument 1 of `read_string` must be buffer, found string"),
+                    "\"..\"",
+                ),
+                (String::from("`read_int` takes 0 arguments, found 1"), "read_int(1)"),
+            ]
+        );
+    }
+    #[test]
+    fn reports_undefined_functions() {
+        let s = "print(twice(2)); print(thrice(1)); twice(1, 2); def twice(x) { x * 2 }
+            def twice(x) { x + x }";
+        assert_eq!(
+            errors(s),
+            [
+                (String::from("`twice` is declared twice"), "def twice(x) { x + x }"),
+                (String::from("there is no function `thrice`"), "thrice(1)"),
+                (String::from("`twice` takes 1 argument, found 2"), "twice(1, 2)"),
+            ]
+        );
+        assert_eq!(
+            errors("print(); print(1, 2)"),
+            [
+                (String::from("`print` takes 1 argument, found 0"), "print()"),
+                (String::from("`print` takes 1 argument, found 2"), "print(1, 2)"),
+            ]
+        );
+    }
+    #[test]
+    fn reports_undefined_variables() {
+        assert_eq!(errors("print(y)"), [(String::from("there is no variable `y`"), "y")]);
+        //functions have a frame of their own, the x outside isn't in it
+        let s = "x sei 3; def f() { x } print(f()); if (true) { z sei 1 } print(z); n sei n + 1";
+        assert_eq!(
+            errors(s),
+            [
+                (String::from("there is no variable `x`"), "x"),
+                (String::from("there is no variable `z`"), "z"),
+                (String::from("there is no variable `n`"), "n"),
+            ]
+        );
+    }
+    #[test]
+    fn checks_annotations() {
+        let s = "print(add(1, true)); add(1); def add(a: int, b: int) -> string { a + b }";
+        assert_eq!(
+            errors(s),
+            [
+                (String::from("argument 2 of `add` must be int, found bool"), "true"),
+                (String::from("`add` takes 2 arguments, found 1"), "add(1)"),
+                (String::from("`add` returns string, found int"), "a + b"),
+            ]
+        );
+        assert_eq!(
+            errors("x: bool sei 1; y sei \"a\"; y: string sei \"b\""),
+            [(String::from("`x` is bool, can't assign int"), "1")]
+        );
+        assert_eq!(
+            errors("def f(s) { if (s < 2) { return \"small\" } print(s) }"),
+            [(String::from("`f` returns string, found int"), "print(s)")]
+        );
+    }
+    #[test]
+    fn checks_floats() {
+        let s = "def half(x) { x / 2.0 } y sei half(3); z sei 1.5 % 2.0; w sei int(2) + float(2.5)";
+        assert_eq!(
+            errors(s),
+            [
+                (String::from("argument 1 of `half` must be float, found int"), "3"),
+                (String::from("argument 1 of `int` must be float, found int"), "2"),
+                (String::from("argument 1 of `float` must be int, found float"), "2.5"),
+                (String::from("`+` can't combine int and float"), "int(2) + float(2.5)"),
+                (String::from("`%` can't be used on float"), "1.5 % 2.0"),
+            ]
+        );
+        let expected = [(String::from("`+` can't be used on bool"), "true + true")];
+        assert_eq!(errors("b sei true + true"), expected);
+    }
+    #[test]
+    fn checks_arrays() {
+        let s = "a sei [1, true]; b sei [0; 3]; b[0] sei \"x\"; c sei 5; c[0]; b[true]; print(b)";
+        assert_eq!(
+            errors(s),
+            [
+                (String::from("array elements must all be int, found bool"), "true"),
+                (String::from("elements are int, can't assign string"), "\"x\""),
+                (String::from("only arrays and vectors can be indexed, found int"), "c"),
+                (String::from("index must be int, found bool"), "true"),
+                (String::from("`print` can't print [int]"), "print(b)"),
+            ]
+        );
+        let s = "n sei 3; a sei [0; n]; def f() { [1] } def g(x) { x[0] + 1 } g([2]); g([[2]])";
+        assert_eq!(
+            errors(s),
+            [
+                (String::from("array length must be a constant of at least 0"), "n"),
+                (String::from("argument 1 of `g` must be [int], found [[int]]"), "[[2]]"),
+                (
+                    String::from("`f` can't return [int], arrays don't outlive the call"),
+                    "def f() { [1] }"
+                ),
+            ]
+        );
+    }
+    #[test]
+    fn checks_structs() {
+        let s = "struct P { x: int, y } struct Q { x, z } p sei P { true, 2 }; P { 1 }; R { 1 };
+        p.w; p.y sei \"s\"; p.y sei 1; def f(a) { a.x } def g(a) { a.z } n sei 5; n.x; print(p)
+        struct S { a } struct S { a, b } S { 1, 2 }";
+        assert_eq!(
+            errors(s),
+            [
+                (String::from("`S` is declared twice"), "struct S { a, b }"),
+                (String::from("field `x` of `P` must be int, found bool"), "true"),
+                (String::from("`P` has 2 fields, found 1"), "P { 1 }"),
+                (String::from("there is no struct `R`"), "R { 1 }"),
+                (String::from("`P` has no field `w`"), "p.w"),
+                (String::from("field `y` is int, can't assign string"), "\"s\""),
+                (String::from("several structs have a field `x`, which one is unclear"), "a.x"),
+                (String::from("int has no fields"), "n"),
+                (String::from("`S` has 1 field, found 2"), "S { 1, 2 }"),
+                (String::from("`print` can't print P"), "print(p)"),
+            ]
+        );
+    }
+    #[test]
+    fn checks_vectors() {
+        let s = "def f(n) { v sei alloc(n); push(v, 1.5); v } v sei f(2); w sei [1]; push(w, 2);
+        push(v, 3); def g(x) { len(x) } g(v); g(w); print(v)";
+        assert_eq!(
+            errors(s),
+            [
+                (String::from("argument 1 of `push` must be vec[int], found [int]"), "w"),
+                (String::from("argument 2 of `push` must be float, found int"), "3"),
+                (String::from("argument 1 of `g` must be vec[float], found [int]"), "w"),
+                (String::from("`print` can't print vec[float]"), "print(v)"),
+            ]
+        );
+    }
+    #[test]
+    fn prints_strings_with_the_string_syscall() {
+        let mut program =
+            parse(String::from("def f(s) { print(s) } f(\"a\"); print(2)"), &default_builders());
+        check(&mut program);
+        let mut calls = vec![];
+        struct Calls<'a>(&'a mut Vec<Callee>);
+        impl Visitor for Calls<'_> {
+            fn visit_call(&mut self, call: &FunctionCall) {
+                self.0.push(call.func.callee());
+            }
+        }
+        Calls(&mut calls).visit_block(&program);
+        assert_eq!(
+            calls,
+            [
+                Callee::Syscall { code: 4, returns: false },
+                Callee::Func(String::from("f")),
+                Callee::Syscall { code: 1, returns: false },
+            ]
+        );
+    }
+}